@@ -0,0 +1,240 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, parse_quote, Data, DeriveInput, Error, Fields, GenericParam, Ident, LitStr, Path
+};
+
+// field attributes, written as #[bluefox(...)]:
+// rename = "key"     the key used in the notation instead of the field name
+// default            a missing key gives Default::default()
+// default = "path"   a missing key gives the result of calling path
+// skip               never written, and always Default::default() when read
+// flatten            the entries of the field are written into, and read from, the parent data
+// function           a String field holding lua source, written as a function instead of a string
+#[derive(Default)]
+struct FieldOptions {
+    rename: Option<String>,
+    default: Option<Option<Path>>,
+    skip: bool,
+    flatten: bool,
+    function: bool
+}
+
+struct Field {
+    ident: Ident,
+    key: String,
+    options: FieldOptions
+}
+
+fn parse_options(attrs: &[syn::Attribute]) -> syn::Result<FieldOptions> {
+    let mut options = FieldOptions::default();
+    for attr in attrs {
+        if !attr.path().is_ident("bluefox") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                options.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+            }
+            else if meta.path.is_ident("default") {
+                if meta.input.peek(syn::Token![=]) {
+                    options.default = Some(Some(meta.value()?.parse::<LitStr>()?.parse()?));
+                }
+                else {
+                    options.default = Some(None);
+                }
+            }
+            else if meta.path.is_ident("skip") {
+                options.skip = true;
+            }
+            else if meta.path.is_ident("flatten") {
+                options.flatten = true;
+            }
+            else if meta.path.is_ident("function") {
+                options.function = true;
+            }
+            else {
+                return Err(meta.error("expected rename, default, skip, flatten or function"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(options)
+}
+
+fn fields(input: &DeriveInput) -> syn::Result<Vec<Field>> {
+    let named = match &input.data {
+        Data::Struct(x) => match &x.fields {
+            Fields::Named(x) => &x.named,
+            _ => return Err(Error::new_spanned(&input.ident, "only structs with named fields can be derived"))
+        },
+        _ => return Err(Error::new_spanned(&input.ident, "only structs with named fields can be derived"))
+    };
+    let mut output = vec![];
+    for field in named {
+        let ident = field.ident.clone().unwrap();
+        let options = parse_options(&field.attrs)?;
+        if options.flatten && (options.function || options.rename.is_some()) {
+            return Err(Error::new_spanned(&ident, "flatten can't be combined with rename or function"));
+        }
+        let key = options.rename.clone().unwrap_or_else(|| ident.to_string());
+        output.push(Field {
+            ident,
+            key,
+            options
+        });
+    }
+    Ok(output)
+}
+
+// the impl gets its own lifetime for the traits, and every type parameter is bound by the trait being derived
+fn generics(input: &DeriveInput, bound: TokenStream2) -> syn::Generics {
+    let mut generics = input.generics.clone();
+    for param in generics.params.iter_mut() {
+        if let GenericParam::Type(x) = param {
+            x.bounds.push(syn::parse2(bound.clone()).unwrap());
+        }
+    }
+    generics.params.insert(0, parse_quote!('__bluefox));
+    generics
+}
+
+#[proc_macro_derive(BluefoxSerialize, attributes(bluefox))]
+pub fn derive_serialize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match serialize(&input) {
+        Ok(x) => x.into(),
+        Err(e) => e.to_compile_error().into()
+    }
+}
+
+fn serialize(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let mut inserts = vec![];
+    for field in fields(input)? {
+        let ident = &field.ident;
+        let key = &field.key;
+        if field.options.skip {
+            continue;
+        }
+        inserts.push(if field.options.flatten {
+            quote! {
+                for (k, v) in ::bluefox_notation::BluefoxSerialize::try_to_data(self.#ident)?.data {
+                    data.data.insert(k, v);
+                }
+            }
+        }
+        else if field.options.function {
+            quote! {
+                data.data.insert(#key.to_owned(), ::bluefox_notation::BluefoxDataType::FUNCTION(self.#ident, None));
+            }
+        }
+        else {
+            quote! {
+                data.data.insert(#key.to_owned(), ::bluefox_notation::IntoBluefoxValue::into_value(self.#ident).map_err(|e| e.context(#key))?);
+            }
+        });
+    }
+
+    let generics = generics(input, quote!(::bluefox_notation::IntoBluefoxValue<'__bluefox>));
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, type_generics, _) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::bluefox_notation::BluefoxSerialize<'__bluefox> for #name #type_generics #where_clause {
+            fn to_data(self) -> ::bluefox_notation::BluefoxData<'__bluefox> {
+                match ::bluefox_notation::BluefoxSerialize::try_to_data(self) {
+                    Ok(x) => x,
+                    Err(e) => panic!("value can't be written as notation: {}", e)
+                }
+            }
+
+            fn try_to_data(self) -> ::std::result::Result<::bluefox_notation::BluefoxData<'__bluefox>, ::bluefox_notation::BluefoxError> {
+                let mut data = ::bluefox_notation::BluefoxData::new();
+                #(#inserts)*
+                Ok(data)
+            }
+        }
+        impl #impl_generics ::bluefox_notation::IntoBluefoxValue<'__bluefox> for #name #type_generics #where_clause {
+            fn into_value(self) -> ::std::result::Result<::bluefox_notation::BluefoxDataType<'__bluefox>, ::bluefox_notation::BluefoxError> {
+                ::bluefox_notation::BluefoxSerialize::try_to_data(self).map(::bluefox_notation::BluefoxDataType::DATA)
+            }
+        }
+    })
+}
+
+#[proc_macro_derive(BluefoxDeserialize, attributes(bluefox))]
+pub fn derive_deserialize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match deserialize(&input) {
+        Ok(x) => x.into(),
+        Err(e) => e.to_compile_error().into()
+    }
+}
+
+fn deserialize(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let mut values = vec![];
+    for field in fields(input)? {
+        let ident = &field.ident;
+        let key = &field.key;
+        if field.options.skip {
+            values.push(quote! { #ident: ::std::default::Default::default() });
+            continue;
+        }
+        if field.options.flatten {
+            values.push(quote! { #ident: ::bluefox_notation::BluefoxDeserialize::from_data(data.clone())? });
+            continue;
+        }
+
+        // a missing key is read as null first, so fields like Option can be left out without an attribute
+        let missing = match &field.options.default {
+            Some(Some(path)) => quote! { #path() },
+            Some(None) => quote! { ::std::default::Default::default() },
+            None if field.options.function => quote! { return Err(::bluefox_notation::BluefoxError::missing_key(#key)) },
+            None => quote! {
+                ::bluefox_notation::FromBluefoxValue::from_value(::bluefox_notation::BluefoxDataType::NULL)
+                    .map_err(|_| ::bluefox_notation::BluefoxError::missing_key(#key))?
+            }
+        };
+        let present = if field.options.function {
+            quote! {
+                match v {
+                    ::bluefox_notation::BluefoxDataType::FUNCTION(x, _) => x,
+                    other => return Err(::bluefox_notation::BluefoxError::type_mismatch(#key, "function", other.type_name()))
+                }
+            }
+        }
+        else {
+            quote! { ::bluefox_notation::FromBluefoxValue::from_value(v).map_err(|e| e.context(#key))? }
+        };
+        values.push(quote! {
+            #ident: match data.data.get(#key).cloned() {
+                Some(v) => #present,
+                None => #missing
+            }
+        });
+    }
+
+    let generics = generics(input, quote!(::bluefox_notation::FromBluefoxValue<'__bluefox>));
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, type_generics, _) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::bluefox_notation::BluefoxDeserialize<'__bluefox> for #name #type_generics #where_clause {
+            #[allow(unused_variables)]
+            fn from_data(data: ::bluefox_notation::BluefoxData<'__bluefox>) -> ::std::result::Result<Self, ::bluefox_notation::BluefoxError> {
+                Ok(Self {
+                    #(#values),*
+                })
+            }
+        }
+        impl #impl_generics ::bluefox_notation::FromBluefoxValue<'__bluefox> for #name #type_generics #where_clause {
+            fn from_value(value: ::bluefox_notation::BluefoxDataType<'__bluefox>) -> ::std::result::Result<Self, ::bluefox_notation::BluefoxError> {
+                match value {
+                    ::bluefox_notation::BluefoxDataType::DATA(x) => ::bluefox_notation::BluefoxDeserialize::from_data(x),
+                    other => Err(::bluefox_notation::BluefoxError::type_mismatch("", "data", other.type_name()))
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,79 @@
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub(crate) fn encode(data: &[u8]) -> String {
+    let mut output = "".to_owned();
+    for chunk in data.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(ALPHABET[(n >> (18 - i * 6) & 63) as usize] as char);
+            }
+            else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+// whitespace is ignored so long blobs can be wrapped over several lines, padding is optional
+pub(crate) fn decode(text: &str) -> Result<Vec<u8>, &'static str> {
+    let mut output = vec![];
+    let mut n: u32 = 0;
+    let mut bits = 0;
+    let mut padding = 0;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if c == '=' {
+            padding += 1;
+            continue;
+        }
+        if padding > 0 {
+            return Err("'=' only at the end of base64 data");
+        }
+        let value = match ALPHABET.iter().position(|x| *x as char == c) {
+            Some(x) => x as u32,
+            None => return Err("base64 data")
+        };
+        n = n << 6 | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((n >> bits) as u8);
+            n &= (1 << bits) - 1;
+        }
+    }
+    // the bits left over tell how many characters the last group had, padding may only fill that group out to four
+    match (bits, padding) {
+        (0, 0) | (4, 0) | (4, 2) | (2, 0) | (2, 1) => {},
+        _ => return Err("base64 data of a valid length")
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64() {
+        assert_eq!(encode(b""), "");
+        assert_eq!(encode(b"f"), "Zg==");
+        assert_eq!(encode(b"fo"), "Zm8=");
+        assert_eq!(encode(b"foo"), "Zm9v");
+        assert_eq!(encode(b"foobar"), "Zm9vYmFy");
+
+        assert_eq!(decode("Zg==").unwrap(), b"f");
+        assert_eq!(decode("Zm8").unwrap(), b"fo");
+        assert_eq!(decode("Zm9v\n    YmFy").unwrap(), b"foobar");
+        assert_eq!(decode(&encode(&[0, 255, 128, 7])).unwrap(), vec![0, 255, 128, 7]);
+
+        assert!(decode("Zm9v!").is_err());
+        assert!(decode("Z").is_err());
+        assert!(decode("Zg==Zg==").is_err());
+    }
+}
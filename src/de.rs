@@ -0,0 +1,333 @@
+use std::fmt;
+use serde::de::{self, Deserialize, DeserializeSeed, IntoDeserializer, Visitor};
+
+use super::{BluefoxData, BluefoxDataType, BluefoxError};
+
+pub fn from_data<'a, T>(data: BluefoxData<'a>) -> Result<T, BluefoxError> where T: de::DeserializeOwned {
+    T::deserialize(ValueDeserializer(BluefoxDataType::DATA(data)))
+}
+
+pub fn from_str<T>(s: &str) -> Result<T, BluefoxError> where T: de::DeserializeOwned {
+    from_data(BluefoxData::try_from(s)?)
+}
+
+impl de::Error for BluefoxError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        BluefoxError::Message(msg.to_string())
+    }
+
+    // the path is filled in by context as the error bubbles up out of nested values
+    fn invalid_type(unexp: de::Unexpected, exp: &dyn de::Expected) -> Self {
+        BluefoxError::type_mismatch("", &exp.to_string(), &unexp.to_string())
+    }
+
+    fn invalid_value(unexp: de::Unexpected, exp: &dyn de::Expected) -> Self {
+        BluefoxError::type_mismatch("", &exp.to_string(), &unexp.to_string())
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        BluefoxError::missing_key(field)
+    }
+}
+
+struct ValueDeserializer<'a>(BluefoxDataType<'a>);
+
+impl <'de, 'a> de::Deserializer<'de> for ValueDeserializer<'a> {
+    type Error = BluefoxError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, BluefoxError> where V: Visitor<'de> {
+        match self.0 {
+            BluefoxDataType::NULL => visitor.visit_unit(),
+            BluefoxDataType::BOOL(x) => visitor.visit_bool(x),
+            BluefoxDataType::INT(x) => visitor.visit_i64(x),
+            BluefoxDataType::FLOAT(x) => visitor.visit_f64(x),
+            BluefoxDataType::STRING(x) => visitor.visit_string(x),
+            BluefoxDataType::BYTES(x) => visitor.visit_byte_buf(x),
+            // dates and times are given as their text, durations the way serde writes std::time::Duration
+            BluefoxDataType::DATETIME(x) => visitor.visit_string(x.to_string()),
+            BluefoxDataType::DATE(x) => visitor.visit_string(x.to_string()),
+            BluefoxDataType::TIME(x) => visitor.visit_string(x.to_string()),
+            BluefoxDataType::DURATION(x) => {
+                let mut data = BluefoxData::new();
+                data.data.insert("secs".to_owned(), BluefoxDataType::INT(x.as_secs() as i64));
+                data.data.insert("nanos".to_owned(), BluefoxDataType::INT(x.subsec_nanos() as i64));
+                visitor.visit_map(DataAccess::new(data))
+            },
+            BluefoxDataType::FUNCTION(x, _) => visitor.visit_string(x),
+            BluefoxDataType::ARRAY(x) => visitor.visit_seq(ArrayAccess { items: x.into_iter(), index: 0 }),
+            BluefoxDataType::DATA(x) => visitor.visit_map(DataAccess::new(x))
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, BluefoxError> where V: Visitor<'de> {
+        match self.0 {
+            BluefoxDataType::NULL => visitor.visit_none(),
+            _ => visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, BluefoxError> where V: Visitor<'de> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // unit variants are written as just the name, other variants as data with the name as the only key
+    fn deserialize_enum<V>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, BluefoxError> where V: Visitor<'de> {
+        match self.0 {
+            BluefoxDataType::STRING(x) => visitor.visit_enum(x.into_deserializer()),
+            BluefoxDataType::DATA(x) if x.data.len() == 1 => {
+                let (variant, value) = x.data.into_iter().next().unwrap();
+                visitor.visit_enum(VariantAccess { variant, value })
+            },
+            other => Err(BluefoxError::type_mismatch("", "enum variant", &describe(&other)))
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+fn describe(value: &BluefoxDataType) -> String {
+    match value {
+        BluefoxDataType::ARRAY(_) => "array".to_owned(),
+        BluefoxDataType::DATA(_) => "data".to_owned(),
+        BluefoxDataType::FUNCTION(..) => "function".to_owned(),
+        other => other.to_string()
+    }
+}
+
+struct ArrayAccess<'a> {
+    items: std::vec::IntoIter<BluefoxDataType<'a>>,
+    index: usize
+}
+impl <'de, 'a> de::SeqAccess<'de> for ArrayAccess<'a> {
+    type Error = BluefoxError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, BluefoxError> where T: DeserializeSeed<'de> {
+        match self.items.next() {
+            Some(x) => {
+                let index = format!("[{}]", self.index);
+                self.index += 1;
+                seed.deserialize(ValueDeserializer(x)).map(Some).map_err(|e| e.context(&index))
+            },
+            None => Ok(None)
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+struct DataAccess<'a> {
+    entries: indexmap::map::IntoIter<String, BluefoxDataType<'a>>,
+    value: Option<(String, BluefoxDataType<'a>)>
+}
+impl <'a> DataAccess<'a> {
+    fn new(data: BluefoxData<'a>) -> Self {
+        Self {
+            entries: data.data.into_iter(),
+            value: None
+        }
+    }
+}
+impl <'de, 'a> de::MapAccess<'de> for DataAccess<'a> {
+    type Error = BluefoxError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, BluefoxError> where K: DeserializeSeed<'de> {
+        match self.entries.next() {
+            Some((k, v)) => {
+                let key = seed.deserialize(KeyDeserializer(k.clone())).map_err(|e| e.context(&k))?;
+                self.value = Some((k, v));
+                Ok(Some(key))
+            },
+            None => Ok(None)
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, BluefoxError> where V: DeserializeSeed<'de> {
+        let (k, v) = self.value.take().ok_or_else(|| BluefoxError::Message("next_value_seed called before next_key_seed".to_owned()))?;
+        seed.deserialize(ValueDeserializer(v)).map_err(|e| e.context(&k))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+// keys are always text, but maps keyed by ints or bools are written with the key as its text, so parse it back
+struct KeyDeserializer(String);
+
+macro_rules! parse_key {
+    ($($method:ident => $visit:ident),*) => {
+        $(fn $method<V>(self, visitor: V) -> Result<V::Value, BluefoxError> where V: Visitor<'de> {
+            match self.0.parse() {
+                Ok(x) => visitor.$visit(x),
+                Err(_) => Err(BluefoxError::type_mismatch("", stringify!($visit).trim_start_matches("visit_"), &self.0))
+            }
+        })*
+    };
+}
+
+impl <'de> de::Deserializer<'de> for KeyDeserializer {
+    type Error = BluefoxError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, BluefoxError> where V: Visitor<'de> {
+        visitor.visit_string(self.0)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, BluefoxError> where V: Visitor<'de> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, BluefoxError> where V: Visitor<'de> {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    parse_key! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8, deserialize_i16 => visit_i16, deserialize_i32 => visit_i32, deserialize_i64 => visit_i64, deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8, deserialize_u16 => visit_u16, deserialize_u32 => visit_u32, deserialize_u64 => visit_u64, deserialize_u128 => visit_u128
+    }
+
+    serde::forward_to_deserialize_any! {
+        f32 f64 char str string bytes byte_buf option unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct VariantAccess<'a> {
+    variant: String,
+    value: BluefoxDataType<'a>
+}
+impl <'de, 'a> de::EnumAccess<'de> for VariantAccess<'a> {
+    type Error = BluefoxError;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self), BluefoxError> where V: DeserializeSeed<'de> {
+        let variant = seed.deserialize(IntoDeserializer::<BluefoxError>::into_deserializer(self.variant.clone()))?;
+        Ok((variant, self))
+    }
+}
+impl <'de, 'a> de::VariantAccess<'de> for VariantAccess<'a> {
+    type Error = BluefoxError;
+
+    fn unit_variant(self) -> Result<(), BluefoxError> {
+        let variant = self.variant;
+        <()>::deserialize(ValueDeserializer(self.value)).map_err(|e| e.context(&variant))
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, BluefoxError> where T: DeserializeSeed<'de> {
+        let variant = self.variant;
+        seed.deserialize(ValueDeserializer(self.value)).map_err(|e| e.context(&variant))
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, BluefoxError> where V: Visitor<'de> {
+        let variant = self.variant;
+        de::Deserializer::deserialize_seq(ValueDeserializer(self.value), visitor).map_err(|e| e.context(&variant))
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, BluefoxError> where V: Visitor<'de> {
+        let variant = self.variant;
+        de::Deserializer::deserialize_map(ValueDeserializer(self.value), visitor).map_err(|e| e.context(&variant))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    enum Mode {
+        Fast,
+        Limited(u32),
+        Custom { name: String }
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Server {
+        host: String,
+        port: u16,
+        tls: Option<bool>,
+        timeout: Duration,
+        modes: Vec<Mode>,
+        #[serde(with = "bytes")]
+        key: Vec<u8>
+    }
+
+    // serde treats Vec<u8> as a sequence, so bytes need to be asked for explicitly
+    mod bytes {
+        use std::fmt;
+        use serde::{de, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(value: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(value)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+            struct BytesVisitor;
+            impl <'de> de::Visitor<'de> for BytesVisitor {
+                type Value = Vec<u8>;
+
+                fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    write!(f, "bytes")
+                }
+
+                fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Vec<u8>, E> {
+                    Ok(v)
+                }
+            }
+            deserializer.deserialize_byte_buf(BytesVisitor)
+        }
+    }
+
+    #[test]
+    fn deserialize() {
+        let test = "
+        host: localhost
+        port: 8080
+        tls: null
+        timeout: 1m30s
+        modes: [Fast, { Limited: 5 }, { Custom: { name: x } }]
+        key: b64\"AP+A\"";
+
+        let server: Server = from_str(test).unwrap();
+        assert_eq!(server, Server {
+            host: "localhost".to_owned(),
+            port: 8080,
+            tls: None,
+            timeout: Duration::from_secs(90),
+            modes: vec![Mode::Fast, Mode::Limited(5), Mode::Custom { name: "x".to_owned() }],
+            key: vec![0, 255, 128]
+        });
+        assert_eq!(from_data::<Server>(crate::to_data(&server).unwrap()).unwrap(), server);
+
+        match from_str::<Server>(&test.replace("Limited: 5", "Limited: -5")) {
+            Err(BluefoxError::TypeMismatch { path, .. }) => assert_eq!(path, "modes[1].Limited"),
+            other => panic!("expected type mismatch, got {:?}", other)
+        }
+        match from_str::<Server>(&test.replace("port: 8080", "")) {
+            Err(BluefoxError::MissingKey { path }) => assert_eq!(path, "port"),
+            other => panic!("expected missing key, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn map_keys() {
+        let names = std::collections::HashMap::from([(1u32, "one".to_owned()), (20, "twenty".to_owned())]);
+        let data = crate::to_data(&names).unwrap();
+        assert_eq!(from_data::<std::collections::HashMap<u32, String>>(data).unwrap(), names);
+
+        let flags = std::collections::BTreeMap::from([(true, 1), (false, 0)]);
+        assert_eq!(from_data::<std::collections::BTreeMap<bool, i32>>(crate::to_data(&flags).unwrap()).unwrap(), flags);
+
+        match from_str::<std::collections::HashMap<u32, String>>("x: one") {
+            Err(BluefoxError::TypeMismatch { path, .. }) => assert_eq!(path, "x"),
+            other => panic!("expected type mismatch, got {:?}", other)
+        }
+    }
+}
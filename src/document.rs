@@ -0,0 +1,272 @@
+use std::{
+    fmt,
+    fs,
+    ops::Range
+};
+
+use super::{BluefoxData, BluefoxDataType, BluefoxError, PathSegment, parse_path, path_to_string};
+use super::parser::{Node, Parser};
+
+// a parsed notation file that remembers its source text, edits only rewrite the
+// bytes of the value being changed so comments, order and formatting survive
+#[derive(Clone, Debug)]
+pub struct Document {
+    source: String,
+    file: Option<String>,
+    data: BluefoxData<'static>,
+    nodes: Vec<Node>
+}
+impl Document {
+    pub fn parse(source: &str) -> Result<Self, BluefoxError> {
+        Self::with_file(source.to_owned(), None)
+    }
+
+    pub fn from_file(file: String) -> Result<Self, BluefoxError> {
+        let source = fs::read_to_string(&file).map_err(|e| BluefoxError::Io { path: file.clone(), source: e })?;
+        Self::with_file(source, Some(file))
+    }
+
+    fn with_file(source: String, file: Option<String>) -> Result<Self, BluefoxError> {
+        let (data, nodes) = Parser::new(&source, file.clone()).parse_document_with_nodes()?;
+        Ok(Self {
+            source,
+            file,
+            data,
+            nodes
+        })
+    }
+
+    pub fn data(&self) -> &BluefoxData<'static> {
+        &self.data
+    }
+
+    // the source text of a value exactly as written in the file
+    pub fn raw(&self, path: &str) -> Result<Option<&str>, BluefoxError> {
+        let path = parse_path(path)?;
+        Ok(self.find(&path).map(|node| &self.source[node.value.clone()]))
+    }
+
+    // replaces the value at path, or adds it after the last entry of its parent table
+    pub fn set(&mut self, path: &str, value: &BluefoxDataType) -> Result<(), BluefoxError> {
+        let segments = parse_path(path)?;
+        if let Some(node) = self.find(&segments) {
+            let text = value.to_string_indented(self.indent_of(node.start));
+            let range = node.value.clone();
+            return self.splice(range, &text);
+        }
+
+        let (last, parent) = segments.split_last().unwrap();
+        let key = match last {
+            PathSegment::Key(x) => x,
+            PathSegment::Index(_) => return Err(BluefoxError::missing_key(path))
+        };
+        let parent_node = if parent.is_empty() {
+            None
+        }
+        else {
+            match self.find(parent) {
+                Some(node) if self.source[node.value.clone()].starts_with('{') => Some(node.clone()),
+                Some(_) => return Err(BluefoxError::type_mismatch(&path_to_string(parent), "data", "value")),
+                None => return Err(BluefoxError::missing_key(&path_to_string(parent)))
+            }
+        };
+        let last_sibling = self.nodes.iter()
+            .filter(|n| n.path.len() == segments.len() && n.path.starts_with(parent))
+            .max_by_key(|n| n.start)
+            .cloned();
+
+        let (parent_indent, indent) = match (&parent_node, &last_sibling) {
+            (Some(p), _) => {
+                let parent_indent = self.indent_of(p.start).to_owned();
+                let indent = match &last_sibling {
+                    Some(s) if self.owns_line(s.start) => self.indent_of(s.start).to_owned(),
+                    _ => parent_indent.clone() + "    "
+                };
+                (parent_indent, indent)
+            },
+            (None, Some(s)) => ("".to_owned(), self.indent_of(s.start).to_owned()),
+            (None, None) => ("".to_owned(), "".to_owned())
+        };
+        let item = key.to_owned() + ": " + &value.to_string_indented(&indent);
+        let entry = indent.clone() + &item;
+
+        match parent_node {
+            None => {
+                let at = match &last_sibling {
+                    Some(s) => self.line_end(s.value.end),
+                    None => self.source.len()
+                };
+                let text = if at == 0 || self.source[..at].ends_with('\n') {
+                    entry + "\n"
+                }
+                else {
+                    "\n".to_owned() + &entry
+                };
+                self.splice(at..at, &text)
+            },
+            Some(p) => {
+                let closing = p.value.end - 1;
+                let after = match &last_sibling {
+                    Some(s) => self.line_end(s.value.end),
+                    None => p.value.start + 1
+                };
+                let inner = p.value.start + 1..closing;
+                if after <= self.line_start(closing) && self.owns_line(closing) {
+                    // the closing brace sits on its own line, so the entry goes just before that line
+                    let at = if last_sibling.is_some() { after } else { self.line_start(closing) };
+                    self.splice(at..at, &(entry + "\n"))
+                }
+                else if let Some(s) = &last_sibling {
+                    // the table is written inline, so the entry is added inline as well
+                    let at = s.value.end;
+                    self.splice(at..at, &(", ".to_owned() + &item))
+                }
+                else if self.source[inner.clone()].trim().is_empty() {
+                    self.splice(inner, &(" ".to_owned() + &item + " "))
+                }
+                else {
+                    self.splice(closing..closing, &("\n".to_owned() + &entry + "\n" + &parent_indent))
+                }
+            }
+        }
+    }
+
+    // removes the entry or array element at path, along with its line if it has one to itself
+    pub fn remove(&mut self, path: &str) -> Result<bool, BluefoxError> {
+        let segments = parse_path(path)?;
+        let node = match self.find(&segments) {
+            Some(x) => x.clone(),
+            None => return Ok(false)
+        };
+        let line_end = self.line_end(node.value.end);
+        let rest = self.source[node.value.end..line_end].trim();
+        let rest = rest.strip_prefix(',').unwrap_or(rest).trim_start();
+        let range = if self.owns_line(node.start) && (rest.is_empty() || rest.starts_with('#') || rest.starts_with("//")) {
+            self.line_start(node.start)..line_end
+        }
+        else {
+            // an inline item takes one of the commas around it along
+            let after = self.source[node.value.end..].trim_start_matches([' ', '\t']);
+            let before = self.source[..node.start].trim_end_matches([' ', '\t']);
+            if let Some(next) = after.strip_prefix(',') {
+                node.start..self.source.len() - next.trim_start_matches([' ', '\t']).len()
+            }
+            else if before.ends_with(',') {
+                before.len() - 1..node.value.end
+            }
+            else {
+                node.start..node.value.end
+            }
+        };
+        self.splice(range, "")?;
+        Ok(true)
+    }
+
+    fn find(&self, path: &[PathSegment]) -> Option<&Node> {
+        // duplicate keys resolve to the last one, same as parsing does
+        self.nodes.iter().rev().find(|n| n.path == path)
+    }
+
+    fn line_start(&self, offset: usize) -> usize {
+        self.source[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0)
+    }
+
+    fn line_end(&self, offset: usize) -> usize {
+        self.source[offset..].find('\n').map(|i| offset + i + 1).unwrap_or(self.source.len())
+    }
+
+    // true if only whitespace comes before offset on its line
+    fn owns_line(&self, offset: usize) -> bool {
+        self.source[self.line_start(offset)..offset].trim().is_empty()
+    }
+
+    fn indent_of(&self, offset: usize) -> &str {
+        let line = &self.source[self.line_start(offset)..offset];
+        &line[..line.len() - line.trim_start().len()]
+    }
+
+    fn splice(&mut self, range: Range<usize>, text: &str) -> Result<(), BluefoxError> {
+        let mut source = self.source.clone();
+        source.replace_range(range, text);
+        // the edited text is parsed again so the data and locations stay in sync, and so a bad edit leaves the document untouched
+        let (data, nodes) = Parser::new(&source, self.file.clone()).parse_document_with_nodes()?;
+        self.source = source;
+        self.data = data;
+        self.nodes = nodes;
+        Ok(())
+    }
+}
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST: &str = "# server settings
+host:   localhost   // where to listen
+port: 8080
+some_data: {
+  /* nested */
+  more_int: 9
+  more_float: 1.67
+}
+some_array: [
+  5
+  6
+]
+";
+
+    #[test]
+    fn edit_in_place() {
+        let mut doc = Document::parse(TEST).unwrap();
+        assert_eq!(doc.raw("host").unwrap(), Some("localhost"));
+
+        doc.set("port", &BluefoxDataType::INT(9090)).unwrap();
+        doc.set("some_data.more_int", &BluefoxDataType::STRING("nine".to_owned())).unwrap();
+        doc.set("some_array[1]", &BluefoxDataType::INT(7)).unwrap();
+        assert_eq!(doc.to_string(), TEST.replace("8080", "9090").replace("9\n", "\"nine\"\n").replace("6\n", "7\n"));
+        assert_eq!(doc.data().data["port"], BluefoxDataType::INT(9090));
+    }
+
+    #[test]
+    fn insert_and_remove() {
+        let mut doc = Document::parse(TEST).unwrap();
+        doc.set("debug", &BluefoxDataType::BOOL(true)).unwrap();
+        doc.set("some_data.more_bool", &BluefoxDataType::BOOL(false)).unwrap();
+        assert!(doc.remove("host").unwrap());
+        assert!(doc.remove("some_array[0]").unwrap());
+        assert!(!doc.remove("missing").unwrap());
+        assert_eq!(doc.to_string(), "# server settings
+port: 8080
+some_data: {
+  /* nested */
+  more_int: 9
+  more_float: 1.67
+  more_bool: false
+}
+some_array: [
+  6
+]
+debug: true
+");
+
+        assert!(doc.set("missing.b", &BluefoxDataType::INT(2)).is_err());
+    }
+
+    #[test]
+    fn inline_edits() {
+        let mut doc = Document::parse("some_data: { a: 1, b: [1, 2, 3] }\nempty: {}\n").unwrap();
+        doc.set("some_data.c", &BluefoxDataType::INT(3)).unwrap();
+        doc.set("empty.d", &BluefoxDataType::BOOL(true)).unwrap();
+        assert_eq!(doc.to_string(), "some_data: { a: 1, b: [1, 2, 3], c: 3 }\nempty: { d: true }\n");
+
+        assert!(doc.remove("some_data.a").unwrap());
+        assert!(doc.remove("some_data.b[2]").unwrap());
+        assert!(doc.remove("some_data.c").unwrap());
+        assert_eq!(doc.to_string(), "some_data: { b: [1, 2] }\nempty: { d: true }\n");
+    }
+}
@@ -0,0 +1,241 @@
+use std::{
+    env,
+    fmt,
+    rc::Rc
+};
+
+use super::{BluefoxData, BluefoxDataType, BluefoxError, PathSegment, path_to_string};
+
+type EnvProvider = Rc<dyn Fn(&str) -> Option<String>>;
+
+// options for from_file_with and parse_with
+#[derive(Clone)]
+pub struct LoadOptions {
+    env: bool,
+    provider: Option<EnvProvider>
+}
+impl LoadOptions {
+    pub fn new() -> Self {
+        Self {
+            env: false,
+            provider: None
+        }
+    }
+
+    // expands `${env:NAME}` and `${env:NAME:-default}` in strings, the default is used when the
+    // variable is unset or empty, a string that is only a variable takes the type its text parses as
+    pub fn env(mut self, value: bool) -> Self {
+        self.env = value;
+        self
+    }
+
+    // reads variables from provider instead of the process environment, and turns expansion on
+    pub fn env_provider<F>(mut self, provider: F) -> Self where F: Fn(&str) -> Option<String> + 'static {
+        self.env = true;
+        self.provider = Some(Rc::new(provider));
+        self
+    }
+
+    fn var(&self, name: &str) -> Option<String> {
+        match &self.provider {
+            Some(f) => f(name),
+            None => env::var(name).ok()
+        }
+    }
+}
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl fmt::Debug for LoadOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoadOptions")
+            .field("env", &self.env)
+            .field("provider", &self.provider.as_ref().map(|_| "<fn>"))
+            .finish()
+    }
+}
+
+// the text of `${env:...}` without the braces, as NAME and the default
+fn split(reference: &str) -> Option<(&str, Option<&str>)> {
+    let reference = reference.strip_prefix("env:")?;
+    Some(match reference.split_once(":-") {
+        Some((name, default)) => (name.trim(), Some(default)),
+        None => (reference.trim(), None)
+    })
+}
+
+fn lookup(reference: &str, path: &[PathSegment], options: &LoadOptions) -> Result<String, BluefoxError> {
+    let (name, default) = split(reference).unwrap();
+    match (options.var(name).filter(|x| !x.is_empty()), default) {
+        (Some(x), _) => Ok(x),
+        (None, Some(x)) => Ok(x.to_owned()),
+        (None, None) => Err(BluefoxError::UnresolvedReference { path: path_to_string(path), reference: reference.to_owned() })
+    }
+}
+
+fn has_function(value: &BluefoxDataType) -> bool {
+    match value {
+        BluefoxDataType::FUNCTION(..) => true,
+        BluefoxDataType::ARRAY(x) => x.iter().any(has_function),
+        BluefoxDataType::DATA(x) => x.data.values().any(has_function),
+        _ => false
+    }
+}
+
+// values from the environment are text, not references, so `${` in them is escaped for resolved
+fn escape(value: &mut BluefoxDataType) {
+    match value {
+        BluefoxDataType::STRING(x) => *x = x.replace("${", "$${"),
+        BluefoxDataType::ARRAY(x) => x.iter_mut().for_each(escape),
+        BluefoxDataType::DATA(x) => x.data.values_mut().for_each(escape),
+        _ => ()
+    }
+}
+
+// other references and `$${` are left for resolved to deal with
+fn expand_string<'a>(text: &str, path: &[PathSegment], options: &LoadOptions) -> Result<Option<BluefoxDataType<'a>>, BluefoxError> {
+    if !text.contains("${env:") {
+        return Ok(None);
+    }
+    let trimmed = text.trim();
+    if trimmed.starts_with("${env:") && trimmed.find('}') == Some(trimmed.len() - 1) {
+        let value = lookup(&trimmed[2..trimmed.len() - 1], path, options)?;
+        // never let the environment inject lua, not even inside an array or data
+        let mut value = match BluefoxDataType::parse(value.clone()) {
+            Ok(x) if !has_function(&x) => x,
+            _ => BluefoxDataType::STRING(value)
+        };
+        escape(&mut value);
+        return Ok(Some(value));
+    }
+
+    let mut output = "".to_owned();
+    let mut rest = text;
+    while let Some(i) = rest.find('$') {
+        output += &rest[..i];
+        rest = &rest[i..];
+        if rest.starts_with("$${") {
+            output += "$${";
+            rest = &rest[3..];
+        }
+        else if rest.starts_with("${env:") {
+            let end = match rest.find('}') {
+                Some(x) => x,
+                None => return Err(BluefoxError::InvalidPath { path: rest[2..].to_owned(), reason: "expected '}' to close reference".to_owned() })
+            };
+            output += &lookup(&rest[2..end], path, options)?.replace("${", "$${");
+            rest = &rest[end + 1..];
+        }
+        else {
+            output.push('$');
+            rest = &rest[1..];
+        }
+    }
+    output += rest;
+    Ok(Some(BluefoxDataType::STRING(output)))
+}
+
+fn expand_value(value: &mut BluefoxDataType, path: &mut Vec<PathSegment>, options: &LoadOptions) -> Result<(), BluefoxError> {
+    match value {
+        BluefoxDataType::STRING(x) => {
+            if let Some(x) = expand_string(x, path, options)? {
+                *value = x;
+            }
+        },
+        BluefoxDataType::ARRAY(x) => {
+            for (i, v) in x.iter_mut().enumerate() {
+                path.push(PathSegment::Index(i));
+                expand_value(v, path, options)?;
+                path.pop();
+            }
+        },
+        BluefoxDataType::DATA(x) => expand_data(x, path, options)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+fn expand_data(data: &mut BluefoxData, path: &mut Vec<PathSegment>, options: &LoadOptions) -> Result<(), BluefoxError> {
+    for (k, v) in data.data.iter_mut() {
+        path.push(PathSegment::Key(k.clone()));
+        expand_value(v, path, options)?;
+        path.pop();
+    }
+    Ok(())
+}
+
+impl <'a> BluefoxData<'a> {
+    pub fn from_file_with(file: String, options: &LoadOptions) -> Result<Self, BluefoxError> {
+        let mut data = BluefoxData::from_file(file)?;
+        data.apply_options(options)?;
+        Ok(data)
+    }
+
+    pub fn parse_with(source: &str, options: &LoadOptions) -> Result<Self, BluefoxError> {
+        let mut data = BluefoxData::try_from(source)?;
+        data.apply_options(options)?;
+        Ok(data)
+    }
+
+    fn apply_options(&mut self, options: &LoadOptions) -> Result<(), BluefoxError> {
+        if options.env {
+            expand_data(self, &mut vec![], options)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn env_expansion() {
+        let vars = HashMap::from([("PORT", "9090"), ("HOST", "example.com"), ("EMPTY", ""), ("HOSTS", "[a, b]"), ("CODE", "`os.exit()`"), ("CODE_ARRAY", "[`return 1`]"), ("CODE_DATA", "{ f: `return 2` }")]);
+        let options = LoadOptions::new().env_provider(move |x| vars.get(x).map(|x| x.to_string()));
+        let source = "
+        port: '${env:PORT:-8080}'
+        timeout: '${env:TIMEOUT:-30s}'
+        debug: '${env:EMPTY:-false}'
+        url: 'http://${env:HOST}:${env:PORT}/${server.path}'
+        hosts: ['${env:HOSTS}', '${env:CODE}']
+        code: ['${env:CODE_ARRAY}', '${env:CODE_DATA}']
+        price: '$${env:PORT}'
+        cost: '$$${env:PORT} $${env:PORT}'";
+        let data = BluefoxData::parse_with(source, &options).unwrap();
+        assert_eq!(data.to_string(), "port: 9090
+timeout: 30s
+debug: false
+url: \"http://example.com:9090/${server.path}\"
+hosts: [[\"a\", \"b\"], \"`os.exit()`\"]
+code: [\"[`return 1`]\", \"{ f: `return 2` }\"]
+price: \"$${env:PORT}\"
+cost: \"$$${env:PORT} $${env:PORT}\"
+");
+
+        // off unless asked for
+        let data = BluefoxData::parse_with(source, &LoadOptions::new()).unwrap();
+        assert_eq!(data.get_as::<String>("port").unwrap(), "${env:PORT:-8080}");
+
+        match BluefoxData::parse_with("a: { b: ['x ${env:MISSING}'] }", &options) {
+            Err(BluefoxError::UnresolvedReference { path, reference }) => assert_eq!((path.as_str(), reference.as_str()), ("a.b[0]", "env:MISSING")),
+            other => panic!("expected unresolved reference, got {:?}", other)
+        }
+
+        // the environment can't reach other values through references
+        let vars = HashMap::from([("LEAK", "${secrets.token}"), ("LEAK_DATA", "{ a: '${secrets.token}' }")]);
+        let options = LoadOptions::new().env_provider(move |x| vars.get(x).map(|x| x.to_string()));
+        let source = "
+        secrets: { token: hunter2 }
+        whole: '${env:LEAK}'
+        mixed: 'token ${env:LEAK}'
+        data: '${env:LEAK_DATA}'";
+        let data = BluefoxData::parse_with(source, &options).unwrap().resolved().unwrap();
+        assert_eq!(data.get_as::<String>("whole").unwrap(), "${secrets.token}");
+        assert_eq!(data.get_as::<String>("mixed").unwrap(), "token ${secrets.token}");
+        assert_eq!(data.get_as::<String>("data.a").unwrap(), "${secrets.token}");
+    }
+}
@@ -0,0 +1,134 @@
+use std::{
+    error::Error,
+    fmt,
+    io
+};
+use mlua::prelude::*;
+
+use super::parser::ParseError;
+
+#[derive(Debug)]
+pub enum BluefoxError {
+    Io { path: String, source: io::Error },
+    Syntax(ParseError),
+    TypeMismatch { path: String, expected: String, found: String },
+    MissingKey { path: String },
+    InvalidPath { path: String, reason: String },
+    Lua { chunk: String, source: LuaError },
+    // a sandboxed call went over one of its limits, limit says which
+    SandboxLimit { chunk: String, limit: String },
+    PatchConflict { path: String, expected: String, found: String },
+    // chain holds the `file:line:column` of every @include leading to the file the error is in, innermost first
+    Include { chain: Vec<String>, source: Box<BluefoxError> },
+    IncludeCycle { chain: Vec<String> },
+    // path is where the reference is written, reference is what it points at
+    UnresolvedReference { path: String, reference: String },
+    ReferenceCycle { chain: Vec<String> },
+    Message(String)
+}
+impl BluefoxError {
+    pub fn type_mismatch(path: &str, expected: &str, found: &str) -> Self {
+        BluefoxError::TypeMismatch {
+            path: path.to_owned(),
+            expected: expected.to_owned(),
+            found: found.to_owned()
+        }
+    }
+
+    pub fn missing_key(path: &str) -> Self {
+        BluefoxError::MissingKey {
+            path: path.to_owned()
+        }
+    }
+
+    // prefixes the path of the error, used when bubbling an error up out of a nested value
+    pub fn context(self, parent: &str) -> Self {
+        let join = |path: String| {
+            if path.is_empty() || path.starts_with('[') {
+                parent.to_owned() + &path
+            }
+            else {
+                parent.to_owned() + "." + &path
+            }
+        };
+        match self {
+            BluefoxError::TypeMismatch { path, expected, found } => BluefoxError::TypeMismatch { path: join(path), expected, found },
+            BluefoxError::MissingKey { path } => BluefoxError::MissingKey { path: join(path) },
+            other => other
+        }
+    }
+}
+impl fmt::Display for BluefoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BluefoxError::Io { path, source } => write!(f, "{}: {}", path, source),
+            BluefoxError::Syntax(x) => write!(f, "{}", x),
+            BluefoxError::TypeMismatch { path, expected, found } if path.is_empty() => write!(f, "expected {}, found {}", expected, found),
+            BluefoxError::TypeMismatch { path, expected, found } => write!(f, "{}: expected {}, found {}", path, expected, found),
+            BluefoxError::MissingKey { path } => write!(f, "missing key \"{}\"", path),
+            BluefoxError::InvalidPath { path, reason } => write!(f, "invalid path \"{}\": {}", path, reason),
+            BluefoxError::Lua { chunk, source } => write!(f, "lua error in \"{}\": {}", chunk, source),
+            BluefoxError::SandboxLimit { chunk, limit } => write!(f, "\"{}\" went over its {}", chunk, limit),
+            BluefoxError::PatchConflict { path, expected, found } => write!(f, "patch conflict at \"{}\": expected {}, found {}", path, expected, found),
+            BluefoxError::Include { chain, source } => write!(f, "{} (included from {})", source, chain.join(", ")),
+            BluefoxError::IncludeCycle { chain } => write!(f, "include cycle: {}", chain.join(" -> ")),
+            BluefoxError::UnresolvedReference { path, reference } => write!(f, "{}: unresolved reference \"${{{}}}\"", path, reference),
+            BluefoxError::ReferenceCycle { chain } => write!(f, "reference cycle: {}", chain.join(" -> ")),
+            BluefoxError::Message(x) => write!(f, "{}", x)
+        }
+    }
+}
+impl Error for BluefoxError {
+    // a syntax error already writes the whole parse error, so it has no source to repeat it
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BluefoxError::Io { source, .. } => Some(source),
+            BluefoxError::Lua { source, .. } => Some(source),
+            BluefoxError::Include { source, .. } => Some(source.as_ref()),
+            _ => None
+        }
+    }
+}
+impl From<ParseError> for BluefoxError {
+    fn from(value: ParseError) -> Self {
+        BluefoxError::Syntax(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BluefoxData;
+
+    #[test]
+    fn error_kinds() {
+        match BluefoxData::from_file("does/not/exist.bfn".to_owned()) {
+            Err(BluefoxError::Io { path, source }) => {
+                assert_eq!(path, "does/not/exist.bfn");
+                assert_eq!(source.kind(), io::ErrorKind::NotFound);
+            },
+            other => panic!("expected io error, got {:?}", other)
+        }
+
+        match BluefoxData::try_from("some_int 4") {
+            Err(e @ BluefoxError::Syntax(_)) => {
+                assert_eq!(e.to_string(), "<string>:1:11: expected ':' after key \"some_int 4\", found end of input");
+                assert!(e.source().is_none());
+            },
+            other => panic!("expected syntax error, got {:?}", other)
+        }
+
+        let lua = Lua::new();
+        let mut data = BluefoxData::try_from("some_function: `error(\"oops\")`").unwrap();
+        match data.execute(&lua, "some_function".to_owned(), ()) {
+            Err(BluefoxError::Lua { chunk, .. }) => assert_eq!(chunk, "some_function"),
+            other => panic!("expected lua error, got {:?}", other)
+        };
+    }
+
+    #[test]
+    fn context() {
+        let err = BluefoxError::missing_key("port").context("server").context("[2]").context("servers");
+        assert_eq!(err.to_string(), "missing key \"servers[2].server.port\"");
+    }
+}
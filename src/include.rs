@@ -0,0 +1,148 @@
+use std::{
+    fs,
+    path::{Path, PathBuf}
+};
+use regex::Regex;
+
+use super::{BluefoxData, BluefoxError, parser::Parser};
+
+// loads a file and everything it includes, `@include "path"` lines at the top level are resolved
+// relative to the including file and merged in order first, so the including file's own keys win
+pub(crate) fn load<'a>(file: &str) -> Result<BluefoxData<'a>, BluefoxError> {
+    load_file(Path::new(file), &mut vec![])
+}
+
+// stack holds the canonical paths of the files currently being loaded, for cycle detection
+fn load_file<'a>(file: &Path, stack: &mut Vec<PathBuf>) -> Result<BluefoxData<'a>, BluefoxError> {
+    let name = file.to_string_lossy().into_owned();
+    let data_string = fs::read_to_string(file).map_err(|e| BluefoxError::Io { path: name.clone(), source: e })?;
+    let canonical = fs::canonicalize(file).map_err(|e| BluefoxError::Io { path: name.clone(), source: e })?;
+    if let Some(i) = stack.iter().position(|x| x == &canonical) {
+        let mut chain: Vec<String> = stack[i..].iter().map(|x| x.to_string_lossy().into_owned()).collect();
+        chain.push(canonical.to_string_lossy().into_owned());
+        return Err(BluefoxError::IncludeCycle { chain });
+    }
+
+    let (data, includes) = Parser::new(&data_string, Some(name.clone())).parse_document_with_includes()?;
+    if includes.is_empty() {
+        return Ok(data);
+    }
+
+    stack.push(canonical);
+    let dir = file.parent().unwrap_or(Path::new(""));
+    let mut output = BluefoxData::new();
+    for (pattern, span) in includes {
+        let location = format!("{}:{}:{}", name, span.line, span.column);
+        let wrap = |e: BluefoxError| match e {
+            BluefoxError::Include { mut chain, source } => {
+                chain.push(location.clone());
+                BluefoxError::Include { chain, source }
+            },
+            BluefoxError::IncludeCycle { chain } => BluefoxError::IncludeCycle { chain },
+            e => BluefoxError::Include { chain: vec![location.clone()], source: Box::new(e) }
+        };
+        for path in expand(&dir.join(&pattern)).map_err(wrap)? {
+            output.merge(load_file(&path, stack).map_err(wrap)?);
+        }
+    }
+    stack.pop();
+    output.merge(data);
+    Ok(output)
+}
+
+// a `*` or `?` in the file name matches every file in the directory it fits, in name order,
+// a pattern matching nothing includes nothing while a plain path has to exist
+fn expand(path: &Path) -> Result<Vec<PathBuf>, BluefoxError> {
+    let pattern = match path.file_name().and_then(|x| x.to_str()) {
+        Some(x) if x.contains(['*', '?']) => x,
+        _ => return Ok(vec![path.to_owned()])
+    };
+    let mut regex = "^".to_owned();
+    for c in pattern.chars() {
+        match c {
+            '*' => regex += ".*",
+            '?' => regex.push('.'),
+            c => regex += &regex::escape(&c.to_string())
+        }
+    }
+    regex.push('$');
+    let regex = Regex::new(&regex).unwrap();
+
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let read_dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let entries = fs::read_dir(read_dir).map_err(|e| BluefoxError::Io { path: dir.to_string_lossy().into_owned(), source: e })?;
+    let mut output = vec![];
+    for entry in entries {
+        let entry = entry.map_err(|e| BluefoxError::Io { path: dir.to_string_lossy().into_owned(), source: e })?;
+        let is_file = entry.file_type().map(|x| x.is_file()).unwrap_or(false);
+        if is_file && entry.file_name().to_str().is_some_and(|x| regex.is_match(x)) {
+            output.push(dir.join(entry.file_name()));
+        }
+    }
+    output.sort();
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, files: &[(&str, &str)]) {
+        let _ = fs::remove_dir_all(dir);
+        for (name, contents) in files {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+    }
+
+    #[test]
+    fn includes() {
+        let dir = std::env::temp_dir().join(format!("bluefox_includes_{}", std::process::id()));
+        write(&dir, &[
+            ("main.bfn", "@include \"common.bfn\"\n@include \"conf.d/*.bfn\"\nname: main\nlimits: { high: 9 }\n@include: not a directive"),
+            ("common.bfn", "name: common\nport: 8080\nlimits: { low: 1, high: 2 }"),
+            ("conf.d/10-port.bfn", "port: 9090"),
+            ("conf.d/20-tags.bfn", "@include \"../tags/base.bfn\"\ntags: [b]"),
+            ("conf.d/readme.txt", "not notation"),
+            ("tags/base.bfn", "tags: [a]\ndebug: true")
+        ]);
+        let data = BluefoxData::from_file(dir.join("main.bfn").to_string_lossy().into_owned()).unwrap();
+        assert_eq!(data.to_string(), "name: \"main\"
+port: 9090
+limits: { low: 1, high: 9 }
+tags: [\"b\"]
+debug: true
+@include: \"not a directive\"
+");
+        // includes only mean something to files
+        assert!(BluefoxData::try_from("@include \"common.bfn\"").is_err());
+
+        write(&dir, &[
+            ("main.bfn", "a: 1\n@include \"inner.bfn\""),
+            ("inner.bfn", "b: 2\n\n@include \"missing.bfn\"")
+        ]);
+        match BluefoxData::from_file(dir.join("main.bfn").to_string_lossy().into_owned()) {
+            Err(BluefoxError::Include { chain, source }) => {
+                assert!(matches!(*source, BluefoxError::Io { .. }));
+                assert_eq!(chain.len(), 2);
+                assert!(chain[0].ends_with("inner.bfn:3:1"));
+                assert!(chain[1].ends_with("main.bfn:2:1"));
+            },
+            other => panic!("expected include error, got {:?}", other)
+        }
+
+        write(&dir, &[
+            ("a.bfn", "@include \"b.bfn\""),
+            ("b.bfn", "@include \"./a.bfn\"")
+        ]);
+        match BluefoxData::from_file(dir.join("a.bfn").to_string_lossy().into_owned()) {
+            Err(BluefoxError::IncludeCycle { chain }) => {
+                assert_eq!(chain.len(), 3);
+                assert!(chain[0].ends_with("a.bfn") && chain[1].ends_with("b.bfn") && chain[2].ends_with("a.bfn"));
+            },
+            other => panic!("expected include cycle, got {:?}", other)
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,15 +1,58 @@
 #![feature(core_intrinsics)]
 
 use std::{
-    collections::HashMap,
-    fs,
-    ops::Index
+    ffi::c_void,
+    ops::Index,
+    time::Duration
 };
-use regex::Regex;
+use indexmap::IndexMap;
 use mlua::prelude::*;
 
+mod base64;
+#[cfg(feature = "serde")]
+mod de;
+mod document;
+mod env;
+mod error;
+mod include;
+mod merge;
+mod parser;
+mod patch;
+mod path;
+mod proxy;
+mod reference;
+mod sandbox;
+mod schema;
+#[cfg(feature = "serde")]
+mod ser;
+mod time;
+mod value;
 //pub mod clib; // todo!
 
+// lets the derive macros refer to ::bluefox_notation from inside this crate too
+extern crate self as bluefox_notation;
+
+pub use indexmap;
+#[cfg(feature = "serde")]
+pub use de::{from_data, from_str};
+#[cfg(feature = "serde")]
+pub use ser::{to_data, to_string};
+pub use document::Document;
+pub use env::LoadOptions;
+pub use error::BluefoxError;
+pub use merge::{Layered, MergeOptions, MergeStrategy};
+pub use parser::{ParseError, Span};
+pub use patch::{Patch, PatchOp};
+pub use path::{PathSegment, parse_path, path_to_string};
+pub use sandbox::Sandbox;
+pub use schema::{Schema, Violation};
+pub use time::{BluefoxDate, BluefoxDateTime, BluefoxTime};
+pub use value::{FromBluefoxValue, IntoBluefoxValue};
+#[cfg(feature = "derive")]
+pub use bluefox_notation_derive::{BluefoxDeserialize, BluefoxSerialize};
+use parser::Parser;
+use time::LuaDuration;
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum BluefoxDataType<'a> {
     NULL,
@@ -17,50 +60,18 @@ pub enum BluefoxDataType<'a> {
     INT(i64),
     FLOAT(f64),
     STRING(String),
+    DATETIME(BluefoxDateTime),
+    DATE(BluefoxDate),
+    TIME(BluefoxTime),
+    DURATION(Duration),
+    BYTES(Vec<u8>),
     FUNCTION(String, Option<LuaFunction<'a>>),
     ARRAY(Vec<BluefoxDataType<'a>>),
     DATA(BluefoxData<'a>)
 }
 impl <'a> BluefoxDataType<'a> {
-    fn parse(item: String) -> Result<Self, String> {
-        Ok(if item == "null" {
-            BluefoxDataType::NULL
-        }
-        else if item == "false" {
-            BluefoxDataType::BOOL(false)
-        }
-        else if item == "true" {
-            BluefoxDataType::BOOL(true)
-        }
-        else if let Ok(x) = item.parse::<i64>() {
-            BluefoxDataType::INT(x)
-        }
-        else if let Ok(x) = item.parse::<f64>() {
-            BluefoxDataType::FLOAT(x)
-        }
-        else if item.starts_with("[") && item.ends_with("]") {
-            let mut output = vec![];
-            let re = Regex::new(r"[\n]").unwrap();
-            let elements: Vec<&str> = re.split(&item[1..item.len() - 1]).collect();
-            for elem in elements {
-                if elem.trim().to_owned() != "" {
-                    output.push(BluefoxDataType::parse(elem.trim().to_owned())?);
-                }
-            }
-            BluefoxDataType::ARRAY(output)
-        }
-        else if item.starts_with("{") && item.ends_with("}") {
-            BluefoxDataType::DATA(BluefoxData::try_from(item[1..item.len() - 1].to_owned())?)
-        }
-        else if item.starts_with("`") && item.ends_with("`") {
-            BluefoxDataType::FUNCTION(item[1..item.len() - 1].to_owned(), None)
-        }
-        else if item.starts_with("\"") && item.ends_with("\"") {
-            BluefoxDataType::STRING(item[1..item.len() - 1].to_owned())
-        }
-        else {
-            BluefoxDataType::STRING(item)
-        })
+    pub fn parse(item: String) -> Result<Self, BluefoxError> {
+        Ok(Parser::new(&item, None).parse_value()?)
     }
 
     pub fn compile<'b>(&'b mut self, lua: &'a Lua) -> LuaResult<()> {
@@ -74,10 +85,26 @@ impl <'a> BluefoxDataType<'a> {
         Err(LuaError::RuntimeError(format!("\"{:?}\" is not a function", self)))
     }
 }
+// lua has no null and a nil in a table is no entry at all, so null goes to lua as this light
+// userdata instead, which functions can reach as `bluefox.null`, note that unlike nil it is truthy
+static LUA_NULL: u8 = 0;
+
+pub(crate) fn lua_null() -> LuaLightUserData {
+    LuaLightUserData(&LUA_NULL as *const u8 as *mut c_void)
+}
+
+// the `bluefox` table functions get in their environment
+pub(crate) fn bluefox_table<'lua>(lua: &'lua Lua) -> LuaResult<LuaTable<'lua>> {
+    let table = lua.create_table()?;
+    table.set("null", lua_null())?;
+    Ok(table)
+}
+
 impl <'a> FromLua<'a> for BluefoxDataType<'a> {
     fn from_lua(value: LuaValue<'a>, lua: &'a Lua) -> LuaResult<Self> {
         Ok(match value.clone() {
             LuaValue::Nil => BluefoxDataType::NULL,
+            LuaValue::LightUserData(x) if x == lua_null() => BluefoxDataType::NULL,
             LuaValue::Boolean(x) => BluefoxDataType::BOOL(x),
             LuaValue::Integer(x) => BluefoxDataType::INT(x),
             LuaValue::Number(x) => {
@@ -88,27 +115,35 @@ impl <'a> FromLua<'a> for BluefoxDataType<'a> {
                     BluefoxDataType::FLOAT(x)
                 }
             },
-            LuaValue::String(x) => BluefoxDataType::STRING(x.to_str()?.to_owned()),
+            // lua strings that aren't valid utf-8 can only be binary
+            LuaValue::String(x) => match x.to_str() {
+                Ok(s) => BluefoxDataType::STRING(s.to_owned()),
+                Err(_) => BluefoxDataType::BYTES(x.as_bytes().to_vec())
+            },
             LuaValue::Function(f) => BluefoxDataType::FUNCTION("nil".to_owned(), Some(f)),
+            LuaValue::UserData(x) if x.is::<BluefoxDateTime>() => BluefoxDataType::DATETIME(*x.borrow::<BluefoxDateTime>()?),
+            LuaValue::UserData(x) if x.is::<BluefoxDate>() => BluefoxDataType::DATE(*x.borrow::<BluefoxDate>()?),
+            LuaValue::UserData(x) if x.is::<BluefoxTime>() => BluefoxDataType::TIME(*x.borrow::<BluefoxTime>()?),
+            LuaValue::UserData(x) if x.is::<LuaDuration>() => BluefoxDataType::DURATION(x.borrow::<LuaDuration>()?.0),
             LuaValue::Table(x) => {
+                // positive integer keys make an array, holes become null as long as there are fewer
+                // of them than values, so a sparse table can't make a huge array
+                let mut items = vec![];
                 let mut is_array = true;
-                for e in x.clone().pairs::<String, LuaValue>() {
-                    if let Ok((k, _)) = e {
-                        if let Err(_) = k.parse::<usize>() {
+                for (k, v) in x.clone().pairs::<String, LuaValue>().flatten() {
+                    match k.parse::<usize>() {
+                        Ok(i) if i >= 1 => items.push((i, v)),
+                        _ => {
                             is_array = false;
                             break;
                         }
                     }
                 }
-                if is_array {
-                    let mut output = vec![];
-                    for _ in x.clone().pairs::<String, LuaValue>() {
-                        output.push(BluefoxDataType::NULL);
-                    }
-                    for e in x.pairs::<String, LuaValue>() {
-                        if let Ok((k, v)) = e {
-                            output[k.parse::<usize>().unwrap() - 1] = BluefoxDataType::from_lua(v, lua)?;
-                        }
+                let len = items.iter().map(|(i, _)| *i).max().unwrap_or(0);
+                if is_array && len <= items.len() * 2 {
+                    let mut output = vec![BluefoxDataType::NULL; len];
+                    for (i, v) in items {
+                        output[i - 1] = BluefoxDataType::from_lua(v, lua)?;
                     }
                     BluefoxDataType::ARRAY(output)
                 }
@@ -123,11 +158,16 @@ impl <'a> FromLua<'a> for BluefoxDataType<'a> {
 impl <'lua> IntoLua<'lua> for BluefoxDataType<'lua> {
     fn into_lua(self, lua: &'lua Lua) -> LuaResult<LuaValue<'lua>> {
         Ok(match self {
-            BluefoxDataType::NULL => LuaValue::Nil,
+            BluefoxDataType::NULL => LuaValue::LightUserData(lua_null()),
             BluefoxDataType::BOOL(x) => LuaValue::Boolean(x),
             BluefoxDataType::INT(x) => LuaValue::Integer(x),
             BluefoxDataType::FLOAT(x) => LuaValue::Number(x),
             BluefoxDataType::STRING(x) => x.to_string().into_lua(lua)?,
+            BluefoxDataType::DATETIME(x) => LuaValue::UserData(lua.create_userdata(x)?),
+            BluefoxDataType::DATE(x) => LuaValue::UserData(lua.create_userdata(x)?),
+            BluefoxDataType::TIME(x) => LuaValue::UserData(lua.create_userdata(x)?),
+            BluefoxDataType::DURATION(x) => LuaValue::UserData(lua.create_userdata(LuaDuration(x))?),
+            BluefoxDataType::BYTES(x) => LuaValue::String(lua.create_string(&x)?),
             BluefoxDataType::FUNCTION(x, c) => {
                 if let Some(f) = c.clone() {
                     LuaValue::Function(f)
@@ -152,25 +192,120 @@ impl <'lua> IntoLua<'lua> for BluefoxDataType<'lua> {
 }
 impl ToString for BluefoxDataType<'_> {
     fn to_string(&self) -> String {
+        self.to_string_indented("")
+    }
+}
+// arrays and data that fit within this many characters are written on one line
+const INLINE_WIDTH: usize = 60;
+
+impl BluefoxDataType<'_> {
+    // nested arrays and data are indented one level deeper than the line they start on
+    pub(crate) fn to_string_indented(&self, indent: &str) -> String {
+        if let BluefoxDataType::ARRAY(_) | BluefoxDataType::DATA(_) = self {
+            if let Some(x) = self.to_string_inline() {
+                if x.chars().count() <= INLINE_WIDTH {
+                    return x;
+                }
+            }
+        }
         match self {
             BluefoxDataType::NULL => "null".to_owned(),
             BluefoxDataType::BOOL(x) => if *x { "true".to_owned() } else { "false".to_owned() },
             BluefoxDataType::INT(x) => x.to_string(),
-            BluefoxDataType::FLOAT(x) => x.to_string(),
-            BluefoxDataType::STRING(x) => "\"".to_owned() + x + "\"",
-            BluefoxDataType::FUNCTION(x, _) => "`".to_owned() + x + "`",
+            BluefoxDataType::FLOAT(x) => float_to_string(*x),
+            BluefoxDataType::STRING(x) => "\"".to_owned() + &escape_string(x) + "\"",
+            BluefoxDataType::DATETIME(x) => x.to_string(),
+            BluefoxDataType::DATE(x) => x.to_string(),
+            BluefoxDataType::TIME(x) => x.to_string(),
+            BluefoxDataType::DURATION(x) => time::duration_to_string(x),
+            BluefoxDataType::BYTES(x) => "b64\"".to_owned() + &base64::encode(x) + "\"",
+            BluefoxDataType::FUNCTION(x, _) => "`".to_owned() + &escape_function(x) + "`",
             BluefoxDataType::ARRAY(x) => {
+                let inner = indent.to_owned() + "    ";
                 let mut output = "[\n".to_owned();
                 for i in x {
-                    output += &(i.to_string() + "\n")[..];
+                    output += &(inner.clone() + &i.to_string_indented(&inner) + "\n")[..];
                 }
+                output += indent;
                 output += "]";
                 output
             },
-            BluefoxDataType::DATA(x) => "{\n".to_owned() + &BluefoxData::to_string(x.clone()) + "\n}"
+            BluefoxDataType::DATA(x) => "{\n".to_owned() + &x.entries_to_string(&(indent.to_owned() + "    ")) + indent + "}"
+        }
+    }
+
+    // functions can span lines, so anything holding one is never written inline
+    fn to_string_inline(&self) -> Option<String> {
+        match self {
+            BluefoxDataType::FUNCTION(..) => None,
+            BluefoxDataType::ARRAY(x) => {
+                let mut items = vec![];
+                for i in x {
+                    items.push(i.to_string_inline()?);
+                }
+                Some("[".to_owned() + &items.join(", ") + "]")
+            },
+            BluefoxDataType::DATA(x) => {
+                if x.data.is_empty() {
+                    return Some("{}".to_owned());
+                }
+                let mut items = vec![];
+                for (k, v) in &x.data {
+                    items.push(k.to_owned() + ": " + &v.to_string_inline()?);
+                }
+                Some("{ ".to_owned() + &items.join(", ") + " }")
+            },
+            x => Some(x.to_string_indented(""))
         }
     }
 }
+// debug formatting is the shortest text that reads back as the same float, and always has a '.' or exponent
+fn float_to_string(value: f64) -> String {
+    if value.is_nan() {
+        if value.is_sign_negative() { "-nan".to_owned() } else { "nan".to_owned() }
+    }
+    else {
+        format!("{:?}", value)
+    }
+}
+fn escape_string(value: &str) -> String {
+    let mut output = "".to_owned();
+    for c in value.chars() {
+        match c {
+            '"' => output += "\\\"",
+            '\\' => output += "\\\\",
+            '\n' => output += "\\n",
+            '\t' => output += "\\t",
+            '\r' => output += "\\r",
+            '\0' => output += "\\0",
+            c if c.is_control() => output += &format!("\\u{{{:x}}}", c as u32),
+            c => output.push(c)
+        }
+    }
+    output
+}
+// backslashes only need doubling when they run into a backtick or the closing backtick
+fn escape_function(value: &str) -> String {
+    let mut output = "".to_owned();
+    let mut run = 0;
+    for c in value.chars() {
+        match c {
+            '\\' => run += 1,
+            '`' => {
+                output += &"\\".repeat(run * 2 + 1);
+                output.push('`');
+                run = 0;
+            },
+            c => {
+                output += &"\\".repeat(run);
+                output.push(c);
+                run = 0;
+            }
+        }
+    }
+    output += &"\\".repeat(run * 2);
+    output
+}
 impl <'a, T> From<Vec<T>> for BluefoxDataType<'a> where T: BluefoxSerialize<'a> + Clone {
     fn from(value: Vec<T>) -> Self {
         let mut vec = vec![];
@@ -183,66 +318,112 @@ impl <'a, T> From<Vec<T>> for BluefoxDataType<'a> where T: BluefoxSerialize<'a>
 
 pub trait BluefoxSerialize<'a> {
     fn to_data(self) -> BluefoxData<'a>;
+
+    // for types holding values notation can't represent, like a u64 past i64::MAX, where to_data panics
+    fn try_to_data(self) -> Result<BluefoxData<'a>, BluefoxError> where Self: Sized {
+        Ok(self.to_data())
+    }
 }
 
 pub trait BluefoxDeserialize<'a>: Sized {
-    fn from_data(data: BluefoxData<'a>) -> Result<Self, String>;
+    fn from_data(data: BluefoxData<'a>) -> Result<Self, BluefoxError>;
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct BluefoxData<'a> {
-    pub data: HashMap<String, BluefoxDataType<'a>>
+    pub data: IndexMap<String, BluefoxDataType<'a>>
 }
 impl <'a> BluefoxData<'a> {
     pub fn new() -> Self {
         Self {
-            data: HashMap::new()
+            data: IndexMap::new()
         }
     }
 
-    pub fn from_file(file: String) -> Result<Self, String> {
-        let data_string = fs::read_to_string(file).map_err(|e| e.to_string())?;
-        BluefoxData::try_from(data_string)
+    // resolves @include directives, see include.rs
+    pub fn from_file(file: String) -> Result<Self, BluefoxError> {
+        include::load(&file)
     }
 
     pub fn to_string<'b, T>(obj: T) -> String where T: BluefoxSerialize<'b> {
         let data = obj.to_data();
 
+        data.entries_to_string("")
+    }
+
+    fn entries_to_string(&self, indent: &str) -> String {
         let mut output = "".to_owned();
-        for (key, elem) in data.data {
-            output += &(key + ": " + &elem.to_string() + "\n");
+        for (key, elem) in &self.data {
+            output += &(indent.to_owned() + key + ": " + &elem.to_string_indented(indent) + "\n");
         }
-
         output
     }
 
-    pub fn execute<'b, A>(&'b mut self, lua: &'a Lua, func: String, args: A) -> Result<BluefoxDataType, LuaError> where A: IntoLuaMulti<'a> {
-        // no need to check if function, lua will error if it isn't
-        let globals = lua.globals();
-        let self_table = self.clone().into_lua(lua)?;
-        globals.set("notation", self_table)?;
+    pub fn execute<'b, A>(&'b mut self, lua: &'a Lua, func: String, args: A) -> Result<BluefoxDataType, BluefoxError> where A: IntoLuaMulti<'a> {
+        self.execute_inner(lua, &func, args).map_err(|e| BluefoxError::Lua { chunk: func, source: e })
+    }
 
-        let re = Regex::new(r"[.\[\]]").unwrap();
-        let path: Vec<&str> = re.split(&func).collect();
-        let path: Vec<&str> = path.into_iter().filter(|s| !s.trim().is_empty()).collect();
+    // the document is handed to the function as `notation` in an environment of its own, which
+    // falls back to the globals, so a global called notation is left alone, see proxy.rs
+    fn execute_inner<'b, A>(&'b mut self, lua: &'a Lua, func: &str, args: A) -> LuaResult<BluefoxDataType<'a>> where A: IntoLuaMulti<'a> {
+        let globals = lua.globals();
+        let metatable = lua.create_table()?;
+        metatable.set("__index", globals.clone())?;
+        metatable.set("__newindex", globals)?;
+        let env = lua.create_table()?;
+        env.set_metatable(Some(metatable));
 
-        let mut target_table: LuaTable = globals.get("notation")?;
-        for i in 0..path.len() - 1 {
-            target_table = target_table.get(path[i])?;
+        proxy::call(self, lua, env, func, args)
+    }
+}
+// func is a path in the same form get_path takes, so indexes count from 0
+fn find_function<'lua>(notation: LuaTable<'lua>, func: &str) -> LuaResult<LuaFunction<'lua>> {
+    let path = parse_path(func).map_err(|e| LuaError::RuntimeError(e.to_string()))?;
+
+    let mut target = LuaValue::Table(notation);
+    for segment in &path {
+        let table = match target {
+            LuaValue::Table(x) => x,
+            other => return Err(LuaError::RuntimeError(format!("\"{}\" is a {}, not data or an array", func, other.type_name())))
+        };
+        target = match segment {
+            PathSegment::Key(x) => table.get(x.as_str())?,
+            PathSegment::Index(i) => table.get(*i as i64 + 1)?
+        };
+    }
+    match target {
+        LuaValue::Function(x) => Ok(x),
+        other => Err(LuaError::RuntimeError(format!("\"{}\" is a {}, not a function", func, other.type_name())))
+    }
+}
+impl BluefoxData<'_> {
+    // lua tables have no order, so keys go back to where they were before and new keys are sorted onto the end
+    fn keep_order(&mut self, previous: Option<&BluefoxData>) {
+        self.data.sort_by(|k1, _, k2, _| {
+            let p1 = previous.and_then(|p| p.data.get_index_of(k1)).unwrap_or(usize::MAX);
+            let p2 = previous.and_then(|p| p.data.get_index_of(k2)).unwrap_or(usize::MAX);
+            (p1, k1).cmp(&(p2, k2))
+        });
+        for (k, v) in self.data.iter_mut() {
+            v.keep_order(previous.and_then(|p| p.data.get(k)));
         }
-
-        let some_function: LuaFunction = if let Ok(i) = path[path.len() - 1].parse::<i64>() {
-            target_table.get(i)?
+    }
+}
+impl BluefoxDataType<'_> {
+    fn keep_order(&mut self, previous: Option<&BluefoxDataType>) {
+        match (self, previous) {
+            (BluefoxDataType::DATA(x), Some(BluefoxDataType::DATA(p))) => x.keep_order(Some(p)),
+            (BluefoxDataType::DATA(x), _) => x.keep_order(None),
+            (BluefoxDataType::ARRAY(x), p) => {
+                for (i, v) in x.iter_mut().enumerate() {
+                    match p {
+                        Some(BluefoxDataType::ARRAY(p)) => v.keep_order(p.get(i)),
+                        _ => v.keep_order(None)
+                    }
+                }
+            },
+            _ => {}
         }
-        else {
-            target_table.get(path[path.len() - 1])?
-        };
-
-        let result = some_function.call::<A, BluefoxDataType>(args)?;
-
-        *self = Self::from_lua(globals.get("notation")?, &lua)?;
-        
-        Ok(result)
     }
 }
 impl <'a> FromLua<'a> for BluefoxData<'a> {
@@ -287,79 +468,14 @@ impl ToString for BluefoxData<'_> {
     }
 }
 impl TryFrom<&str> for BluefoxData<'_> {
-    type Error = String;
-    fn try_from(value: &str) -> Result<Self, String> {
-        let re = Regex::new(r#"[:'`\n{}\[\]\\]"#).unwrap();
-        let mut keywords: Vec<String> = vec![];
-
-        let mut last_end = 0;
-        for cap in re.captures_iter(value) {
-            let start = cap.get(0).unwrap().start();
-            let end = cap.get(0).unwrap().end();
-
-            keywords.push(value[last_end..start].to_owned());
-            keywords.push(value[start..end].to_owned());
-
-            last_end = end;
-        }
-        keywords.push(value[last_end..value.len()].to_owned());
-
-        let mut strings: Vec<String> = vec![];
-        let mut accumulator = "".to_owned();
-        let mut encapsulator = "".to_owned();
-        for (i, item) in keywords.clone().into_iter().enumerate() {
-            if item == "" {
-                continue;
-            }
-            if (item == encapsulator || item == "}" || item == "]") && item != "{" && item != "[" && keywords[i - 1] != "\\" {
-                strings.push(accumulator.clone() + &item);
-                accumulator = "".to_owned();
-                encapsulator = "".to_owned();
-                continue;
-            }
-            else if encapsulator == "" && (item == "\"" || item == "'" || item == "`" || item == "{" || item == "[") {
-                encapsulator = item.to_owned();
-            }
-            if encapsulator == "" {
-                strings.push(item.to_owned());
-            }
-            else {
-                accumulator += &item;
-            }
-        }
-
-        let mut trimmed = vec![];
-        for s in strings {
-            let trim = s.trim();
-            if trim != "" {
-                trimmed.push(trim.to_owned());
-            }
-        }
-
-        let mut data = HashMap::new();
-        let mut current_key = "".to_owned();
-        for (i, item) in trimmed.clone().into_iter().enumerate() {
-            if item == ":" { continue; }
-            if current_key != "" {
-                data.insert(current_key, BluefoxDataType::parse(item)?);
-                current_key = "".to_owned();
-            }
-            else if i + 1 < trimmed.len() && trimmed[i + 1] == ":" {
-                current_key = item.clone();
-            }
-            else {
-                return Err("Expected \":\" after ".to_owned() + &item);
-            }
-        }
-
-        Ok(Self {
-            data
-        })
+    type Error = BluefoxError;
+    fn try_from(value: &str) -> Result<Self, BluefoxError> {
+        Ok(Parser::new(value, None).parse_document()?)
     }
 }
 impl TryFrom<String> for BluefoxData<'_> {
-    type Error = String;
-    fn try_from(value: String) -> Result<Self, String> {
+    type Error = BluefoxError;
+    fn try_from(value: String) -> Result<Self, BluefoxError> {
         BluefoxData::try_from(&value[..])
     }
 }
@@ -477,6 +593,9 @@ mod tests {
         some_quote: \"this is the, second test string\"
         some_function: `
             notation.some_int = 8
+            notation.null_copy = notation.is_null
+            notation.null_array = { 1, bluefox.null, 3 }
+            notation.null_data = { a = bluefox.null }
             return notation.some_data.more_float
         `
         some_array: [
@@ -497,7 +616,11 @@ mod tests {
         assert_eq!(data.execute(&lua, "some_function".to_owned(), ()).unwrap(), BluefoxDataType::FLOAT(1.67));
         assert_eq!(data.data["some_int"], BluefoxDataType::INT(8));
 
-        assert_eq!(data.data.get("is_null").clone(), None); // when converted back from lua, null values are ignored
+        assert_eq!(data.data.get("is_null").clone(), Some(&BluefoxDataType::NULL)); // null is bluefox.null in lua, so it survives the round trip
+        assert_eq!(data.data.get("null_copy").clone(), Some(&BluefoxDataType::NULL));
+        assert_eq!(data.data["null_array"].to_string(), "[1, null, 3]");
+        assert_eq!(data.data["null_data"].to_string(), "{ a: null }");
+        assert_eq!(BluefoxDataType::from_lua(BluefoxDataType::NULL.into_lua(&lua).unwrap(), &lua).unwrap(), BluefoxDataType::NULL);
         assert_eq!(data.data.get("some_bool").unwrap().clone(), BluefoxDataType::BOOL(false));
         assert_eq!(data.data.get("some_float").unwrap().clone(), BluefoxDataType::FLOAT(6.4));
         assert_eq!(data.data.get("some_string").unwrap().clone(), BluefoxDataType::STRING("this is the, first test string".to_owned()));
@@ -516,6 +639,171 @@ mod tests {
         drop(data);
     }
 
+    #[test]
+    fn string_round_trip() {
+        let mut strings = vec![
+            "".to_owned(),
+            "\"".to_owned(),
+            "\\".to_owned(),
+            "ends with a backslash \\".to_owned(),
+            "\\\\`\\ \\\\\\".to_owned(),
+            "line one\nline two\r\n\ttabbed".to_owned(),
+            "`backticks` and } braces ] and: colons # not a comment".to_owned(),
+            "\\u{41} is not an escape once escaped".to_owned(),
+            "null".to_owned(),
+            "\u{0}\u{7}\u{1b}\u{7f}\u{85}".to_owned(),
+            "unicode \u{1F98A} \u{e9}".to_owned()
+        ];
+        strings.push((0..0x250).filter_map(char::from_u32).collect());
+
+        for s in strings {
+            let mut data = BluefoxData::new();
+            data.data.insert("some_string".to_owned(), BluefoxDataType::STRING(s.clone()));
+            data.data.insert("some_function".to_owned(), BluefoxDataType::FUNCTION(s.clone(), None));
+            let data = BluefoxData::try_from(data.to_string()).unwrap();
+            assert_eq!(data.data["some_string"], BluefoxDataType::STRING(s.clone()));
+            assert_eq!(data.data["some_function"], BluefoxDataType::FUNCTION(s, None));
+        }
+    }
+
+    #[test]
+    fn inline_serializer() {
+        let test = "
+        short_array: [
+            1
+            2
+        ]
+        nested: [[1, 2], [3]]
+        short_data: {
+            a: 1
+        }
+        long_array: [\"aaaaaaaaaaaaaaaaaaaa\", \"bbbbbbbbbbbbbbbbbbbb\", \"cccccccccccccccccccc\"]
+        function_array: [`return 1`]";
+
+        let data = BluefoxData::try_from(test).unwrap();
+        let output = data.to_string();
+        assert_eq!(output, "short_array: [1, 2]
+nested: [[1, 2], [3]]
+short_data: { a: 1 }
+long_array: [
+    \"aaaaaaaaaaaaaaaaaaaa\"
+    \"bbbbbbbbbbbbbbbbbbbb\"
+    \"cccccccccccccccccccc\"
+]
+function_array: [
+    `return 1`
+]
+");
+        assert_eq!(BluefoxData::try_from(output).unwrap(), data);
+    }
+
+    #[test]
+    fn float_round_trip() {
+        let floats = [
+            0.0, -0.0, 1.0, -1.5, 0.1, 1.0 / 3.0, 1e300, 1e-300, 123456789.0, 1e15, 1e16,
+            f64::MAX, f64::MIN, f64::MIN_POSITIVE, f64::EPSILON, 5e-324,
+            f64::INFINITY, f64::NEG_INFINITY, f64::NAN, -f64::NAN
+        ];
+        for x in floats {
+            let mut data = BluefoxData::new();
+            data.data.insert("some_float".to_owned(), BluefoxDataType::FLOAT(x));
+            let data = BluefoxData::try_from(data.to_string()).unwrap();
+            if let BluefoxDataType::FLOAT(y) = data.data["some_float"] {
+                if x.is_nan() {
+                    assert!(y.is_nan() && x.is_sign_negative() == y.is_sign_negative());
+                }
+                else {
+                    assert_eq!(x.to_bits(), y.to_bits());
+                }
+            }
+            else {
+                assert_eq!(x.to_string(), "is not recognized as a float");
+            }
+        }
+    }
+
+    #[test]
+    fn temporal_values() {
+        let test = "
+        some_datetime: 2024-01-01T00:00:00Z
+        some_date: 2024-01-01
+        some_time: 08:30:00.5
+        some_timeout: 1m30s
+        some_function: `
+            notation.later = notation.some_timeout
+            return tostring(notation.some_date) .. ' ' .. notation.some_time.hour .. ' ' .. notation.some_timeout.seconds
+        `";
+
+        let lua = Lua::new();
+
+        let mut data = BluefoxData::try_from(test).unwrap();
+        assert_eq!(data.data["some_date"], BluefoxDataType::DATE(BluefoxDate { year: 2024, month: 1, day: 1 }));
+        assert_eq!(data.data["some_timeout"], BluefoxDataType::DURATION(Duration::from_secs(90)));
+        assert_eq!(BluefoxData::try_from(data.to_string()).unwrap(), data);
+
+        assert_eq!(data.execute(&lua, "some_function".to_owned(), ()).unwrap(), BluefoxDataType::STRING("2024-01-01 8 90.0".to_owned()));
+        assert_eq!(data.data["later"], BluefoxDataType::DURATION(Duration::from_secs(90)));
+        assert!(matches!(data.data["some_datetime"], BluefoxDataType::DATETIME(_)));
+        assert!(matches!(data.data["some_time"], BluefoxDataType::TIME(_)));
+
+        assert!(BluefoxData::try_from("some_date: 2024-02-30").is_err());
+    }
+
+    #[test]
+    fn bytes_values() {
+        let test = "
+        some_bytes: b64\"AP+A\"
+        some_key: b64\"
+            Zm9v
+            YmFy
+        \"
+        some_function: `
+            notation.copy = notation.some_bytes
+            return #notation.some_bytes .. ' ' .. notation.some_bytes:byte(2) .. ' ' .. notation.some_key
+        `";
+
+        let lua = Lua::new();
+
+        let mut data = BluefoxData::try_from(test).unwrap();
+        assert_eq!(data.data["some_bytes"], BluefoxDataType::BYTES(vec![0, 255, 128]));
+        assert_eq!(data.data["some_key"], BluefoxDataType::BYTES(b"foobar".to_vec()));
+        assert_eq!(data.data["some_bytes"].to_string(), "b64\"AP+A\"");
+        assert_eq!(BluefoxData::try_from(data.to_string()).unwrap(), data);
+
+        assert_eq!(data.execute(&lua, "some_function".to_owned(), ()).unwrap(), BluefoxDataType::STRING("3 255 foobar".to_owned()));
+        assert_eq!(data.data["copy"], BluefoxDataType::BYTES(vec![0, 255, 128]));
+
+        assert!(BluefoxData::try_from("some_bytes: b64\"not base64!\"").is_err());
+        assert!(BluefoxData::try_from("some_bytes: b64\"AP+A").is_err());
+    }
+
+    #[test]
+    fn key_order() {
+        let test = "
+        zebra: 1
+        apple: {
+            yak: true
+            bee: false
+        }
+        mango: `notation.kiwi = 3`";
+
+        let lua = Lua::new();
+
+        let mut data = BluefoxData::try_from(test).unwrap();
+        assert_eq!(data.to_string(), "zebra: 1\napple: { yak: true, bee: false }\nmango: `notation.kiwi = 3`\n");
+
+        data.execute(&lua, "mango".to_owned(), ()).unwrap();
+        let keys: Vec<&String> = data.data.keys().collect();
+        assert_eq!(keys, vec!["zebra", "apple", "mango", "kiwi"]);
+        if let BluefoxDataType::DATA(apple) = &data.data["apple"] {
+            let keys: Vec<&String> = apple.data.keys().collect();
+            assert_eq!(keys, vec!["yak", "bee"]);
+        }
+        else {
+            assert_eq!("apple", "is not recognized as data");
+        }
+    }
+
     #[test]
     fn lua_nested_function() {
         let test = r#"
@@ -532,10 +820,17 @@ mod tests {
         let lua = Lua::new();
 
         let mut data = BluefoxData::try_from(test).unwrap();
+        if let Some(BluefoxDataType::DATA(x)) = data.get_path_mut("some_data").unwrap() {
+            x.data.insert("dotted.key".to_owned(), BluefoxDataType::FUNCTION("return 4".to_owned(), None));
+        }
 
         assert_eq!(data.execute(&lua, "outside_function".to_owned(), ()).unwrap(), BluefoxDataType::INT(1));
-        assert_eq!(data.execute(&lua, "some_array.1".to_owned(), ()).unwrap(), BluefoxDataType::INT(2));
-        assert_eq!(data.execute(&lua, "some_array[1]".to_owned(), ()).unwrap(), BluefoxDataType::INT(2)); // tests both the array indexing and reusing compiled functions
+        assert_eq!(data.execute(&lua, "some_array[0]".to_owned(), ()).unwrap(), BluefoxDataType::INT(2));
+        assert_eq!(data.execute(&lua, "some_array[0]".to_owned(), ()).unwrap(), BluefoxDataType::INT(2)); // tests both the array indexing and reusing compiled functions
         assert_eq!(data.execute(&lua, "some_data.data_function".to_owned(), ()).unwrap(), BluefoxDataType::INT(3));
+        assert_eq!(data.execute(&lua, "some_data[\"dotted.key\"]".to_owned(), ()).unwrap(), BluefoxDataType::INT(4));
+        assert!(data.get_path("some_data[\"dotted.key\"]").unwrap().is_some());
+        assert!(data.execute(&lua, "".to_owned(), ()).is_err());
+        assert!(data.execute(&lua, "some_array[1]".to_owned(), ()).is_err());
     }
 }
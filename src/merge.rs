@@ -0,0 +1,288 @@
+use std::{
+    collections::HashMap,
+    io
+};
+
+use super::{BluefoxData, BluefoxDataType, BluefoxError, PathSegment, parse_path, path_to_string};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeStrategy {
+    // the overlay value takes the place of the base value
+    Replace,
+    // data is merged key by key, arrays are replaced
+    Deep,
+    // data is merged key by key, arrays are appended to
+    Append
+}
+
+#[derive(Clone, Debug)]
+pub struct MergeOptions {
+    default: MergeStrategy,
+    strategies: HashMap<String, MergeStrategy>,
+    null_deletes: bool
+}
+impl MergeOptions {
+    pub fn new() -> Self {
+        Self {
+            default: MergeStrategy::Deep,
+            strategies: HashMap::new(),
+            null_deletes: false
+        }
+    }
+
+    pub fn default_strategy(mut self, strategy: MergeStrategy) -> Self {
+        self.default = strategy;
+        self
+    }
+
+    // the strategy for the value at path, and everything under it unless set again further down
+    pub fn strategy(mut self, path: &str, strategy: MergeStrategy) -> Self {
+        self.strategies.insert(path.to_owned(), strategy);
+        self
+    }
+
+    // an explicit null in the overlay removes the key instead of setting it to null
+    pub fn null_deletes(mut self, value: bool) -> Self {
+        self.null_deletes = value;
+        self
+    }
+
+    fn strategy_for(&self, path: &[PathSegment]) -> MergeStrategy {
+        for n in (1..=path.len()).rev() {
+            if let Some(x) = self.strategies.get(&path_to_string(&path[..n])) {
+                return *x;
+            }
+        }
+        self.default
+    }
+}
+impl Default for MergeOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// the layer every path was last written by, keyed by the path as a string
+struct Origins<'o> {
+    paths: &'o mut HashMap<String, usize>,
+    layer: usize
+}
+impl Origins<'_> {
+    fn set(&mut self, path: &[PathSegment], value: &BluefoxDataType) {
+        self.paths.insert(path_to_string(path), self.layer);
+        let mut path = path.to_vec();
+        match value {
+            BluefoxDataType::DATA(x) => {
+                for (k, v) in &x.data {
+                    path.push(PathSegment::Key(k.clone()));
+                    self.set(&path, v);
+                    path.pop();
+                }
+            },
+            BluefoxDataType::ARRAY(x) => {
+                for (i, v) in x.iter().enumerate() {
+                    path.push(PathSegment::Index(i));
+                    self.set(&path, v);
+                    path.pop();
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn clear(&mut self, path: &[PathSegment]) {
+        let path = path_to_string(path);
+        self.paths.retain(|k, _| {
+            !(k == &path || (k.starts_with(&path) && (k[path.len()..].starts_with('.') || k[path.len()..].starts_with('['))))
+        });
+    }
+}
+
+fn merge_data<'a>(base: &mut BluefoxData<'a>, overlay: BluefoxData<'a>, path: &mut Vec<PathSegment>, options: &MergeOptions, origins: &mut Option<Origins>) {
+    for (k, v) in overlay.data {
+        path.push(PathSegment::Key(k.clone()));
+        let strategy = options.strategy_for(path);
+        match (base.data.get_mut(&k), v) {
+            (_, BluefoxDataType::NULL) if options.null_deletes => {
+                base.data.shift_remove(&k);
+                if let Some(o) = origins {
+                    o.clear(path);
+                }
+            },
+            (Some(BluefoxDataType::DATA(b)), BluefoxDataType::DATA(v)) if strategy != MergeStrategy::Replace => {
+                if let Some(o) = origins {
+                    o.paths.insert(path_to_string(path), o.layer);
+                }
+                merge_data(b, v, path, options, origins);
+            },
+            (Some(BluefoxDataType::ARRAY(b)), BluefoxDataType::ARRAY(v)) if strategy == MergeStrategy::Append => {
+                if let Some(o) = origins {
+                    o.paths.insert(path_to_string(path), o.layer);
+                    for (i, item) in v.iter().enumerate() {
+                        path.push(PathSegment::Index(b.len() + i));
+                        o.set(path, item);
+                        path.pop();
+                    }
+                }
+                b.extend(v);
+            },
+            (_, v) => {
+                if let Some(o) = origins {
+                    o.clear(path);
+                    o.set(path, &v);
+                }
+                base.data.insert(k, v);
+            }
+        }
+        path.pop();
+    }
+}
+
+impl <'a> BluefoxData<'a> {
+    // merges overlay into self, keys new to self are added at the end
+    pub fn merge(&mut self, overlay: BluefoxData<'a>) {
+        self.merge_with(overlay, &MergeOptions::new());
+    }
+
+    pub fn merge_with(&mut self, overlay: BluefoxData<'a>, options: &MergeOptions) {
+        merge_data(self, overlay, &mut vec![], options, &mut None);
+    }
+}
+
+// merges a stack of layers in order, later layers win, and remembers which layer each value came from
+#[derive(Clone, Debug)]
+pub struct Layered<'a> {
+    options: MergeOptions,
+    layers: Vec<String>,
+    data: BluefoxData<'a>,
+    origins: HashMap<String, usize>
+}
+impl <'a> Layered<'a> {
+    pub fn new() -> Self {
+        Self::with_options(MergeOptions::new())
+    }
+
+    pub fn with_options(options: MergeOptions) -> Self {
+        Self {
+            options,
+            layers: vec![],
+            data: BluefoxData::new(),
+            origins: HashMap::new()
+        }
+    }
+
+    pub fn add(&mut self, name: &str, layer: BluefoxData<'a>) {
+        let mut origins = Some(Origins {
+            paths: &mut self.origins,
+            layer: self.layers.len()
+        });
+        self.layers.push(name.to_owned());
+        merge_data(&mut self.data, layer, &mut vec![], &self.options, &mut origins);
+    }
+
+    // the layer is named after the file
+    pub fn add_file(&mut self, file: String) -> Result<(), BluefoxError> {
+        let layer = BluefoxData::from_file(file.clone())?;
+        self.add(&file, layer);
+        Ok(())
+    }
+
+    // for override files that don't have to exist, returns false if the file wasn't there
+    pub fn add_optional_file(&mut self, file: String) -> Result<bool, BluefoxError> {
+        match BluefoxData::from_file(file.clone()) {
+            Ok(layer) => {
+                self.add(&file, layer);
+                Ok(true)
+            },
+            Err(BluefoxError::Io { source, .. }) if source.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e)
+        }
+    }
+
+    pub fn layers(&self) -> &[String] {
+        &self.layers
+    }
+
+    pub fn data(&self) -> &BluefoxData<'a> {
+        &self.data
+    }
+
+    pub fn into_data(self) -> BluefoxData<'a> {
+        self.data
+    }
+
+    // the name of the layer that last set the value at path
+    pub fn origin(&self, path: &str) -> Result<Option<&str>, BluefoxError> {
+        let path = path_to_string(&parse_path(path)?);
+        Ok(self.origins.get(&path).map(|i| &self.layers[*i][..]))
+    }
+}
+impl Default for Layered<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "
+    host: localhost
+    port: 8080
+    tags: [a, b]
+    tls: { enabled: false, cert: none }
+    limits: { low: 1, high: 2 }";
+
+    const OVERLAY: &str = "
+    port: 9090
+    tags: [c]
+    tls: { enabled: true }
+    limits: { high: 5 }
+    debug: true
+    host: null";
+
+    #[test]
+    fn merge() {
+        let mut data = BluefoxData::try_from(BASE).unwrap();
+        data.merge(BluefoxData::try_from(OVERLAY).unwrap());
+        assert_eq!(data.to_string(), "host: null
+port: 9090
+tags: [\"c\"]
+tls: { enabled: true, cert: \"none\" }
+limits: { low: 1, high: 5 }
+debug: true
+");
+
+        let options = MergeOptions::new()
+            .strategy("tags", MergeStrategy::Append)
+            .strategy("limits", MergeStrategy::Replace)
+            .null_deletes(true);
+        let mut data = BluefoxData::try_from(BASE).unwrap();
+        data.merge_with(BluefoxData::try_from(OVERLAY).unwrap(), &options);
+        assert_eq!(data.to_string(), "port: 9090
+tags: [\"a\", \"b\", \"c\"]
+tls: { enabled: true, cert: \"none\" }
+limits: { high: 5 }
+debug: true
+");
+    }
+
+    #[test]
+    fn layered() {
+        let mut layered = Layered::with_options(MergeOptions::new().strategy("tags", MergeStrategy::Append).null_deletes(true));
+        layered.add("base", BluefoxData::try_from(BASE).unwrap());
+        layered.add("production", BluefoxData::try_from(OVERLAY).unwrap());
+        assert!(!layered.add_optional_file("does/not/exist.bfn".to_owned()).unwrap());
+        assert!(layered.add_file("does/not/exist.bfn".to_owned()).is_err());
+
+        assert_eq!(layered.layers(), ["base".to_owned(), "production".to_owned()]);
+        assert_eq!(layered.origin("port").unwrap(), Some("production"));
+        assert_eq!(layered.origin("tls.cert").unwrap(), Some("base"));
+        assert_eq!(layered.origin("tls.enabled").unwrap(), Some("production"));
+        assert_eq!(layered.origin("tags[1]").unwrap(), Some("base"));
+        assert_eq!(layered.origin("tags[2]").unwrap(), Some("production"));
+        assert_eq!(layered.origin("host").unwrap(), None);
+        assert_eq!(layered.data().get_as::<i64>("limits.high").unwrap(), 5);
+    }
+}
@@ -0,0 +1,819 @@
+use std::{
+    fmt,
+    ops::Range
+};
+
+use super::{BluefoxData, BluefoxDataType, PathSegment, base64, time};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub file: Option<String>,
+    pub span: Span,
+    pub expected: String,
+    pub found: String
+}
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let file = self.file.as_deref().unwrap_or("<string>");
+        write!(f, "{}:{}:{}: expected {}, found {}", file, self.span.line, self.span.column, self.expected, self.found)
+    }
+}
+impl std::error::Error for ParseError {}
+
+fn describe(c: Option<char>) -> String {
+    match c {
+        None => "end of input".to_owned(),
+        Some('\n') => "end of line".to_owned(),
+        Some(c) => format!("'{}'", c)
+    }
+}
+
+// character cursor over the source, keeps track of where we are for error reporting
+struct Lexer<'s> {
+    src: &'s str,
+    pos: usize,
+    line: usize,
+    column: usize
+}
+impl <'s> Lexer<'s> {
+    fn new(src: &'s str) -> Self {
+        Self {
+            src,
+            pos: 0,
+            line: 1,
+            column: 1
+        }
+    }
+
+    fn span(&self) -> Span {
+        Span {
+            line: self.line,
+            column: self.column
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        }
+        else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn at(&self, s: &str) -> bool {
+        self.src[self.pos..].starts_with(s)
+    }
+
+    // comments are `# ...` and `// ...` to the end of the line, or `/* ... */`
+    fn at_comment(&self) -> bool {
+        self.at("#") || self.at("//") || self.at("/*")
+    }
+
+    // skips a comment if there is one here, the error holds where an unterminated block comment starts
+    fn skip_comment(&mut self) -> Result<bool, Span> {
+        let start = self.span();
+        if self.at("/*") {
+            self.bump();
+            self.bump();
+            while !self.at("*/") {
+                if self.bump().is_none() {
+                    return Err(start);
+                }
+            }
+            self.bump();
+            self.bump();
+            return Ok(true);
+        }
+        if self.at_comment() {
+            while let Some(c) = self.peek() {
+                if c == '\n' {
+                    break;
+                }
+                self.bump();
+            }
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    // skips whitespace and comments, newlines are only skipped if asked to
+    fn skip_whitespace(&mut self, newlines: bool) -> Result<(), Span> {
+        loop {
+            match self.peek() {
+                Some('\n') if !newlines => break,
+                Some(c) if c.is_whitespace() => {
+                    self.bump();
+                },
+                _ => {
+                    if !self.skip_comment()? {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// where an entry or array element sits in the source, in byte offsets
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Node {
+    pub path: Vec<PathSegment>,
+    pub start: usize,
+    pub value: Range<usize>
+}
+
+pub(crate) struct Parser<'s> {
+    lexer: Lexer<'s>,
+    file: Option<String>,
+    path: Vec<PathSegment>,
+    nodes: Option<Vec<Node>>,
+    includes: Option<Vec<(String, Span)>>,
+    value_end: usize
+}
+impl <'s> Parser<'s> {
+    pub(crate) fn new(src: &'s str, file: Option<String>) -> Self {
+        Self {
+            lexer: Lexer::new(src),
+            file,
+            path: vec![],
+            nodes: None,
+            includes: None,
+            value_end: 0
+        }
+    }
+
+    fn record(&mut self, start: usize, value_start: usize) {
+        if let Some(nodes) = &mut self.nodes {
+            nodes.push(Node {
+                path: self.path.clone(),
+                start,
+                value: value_start..self.value_end
+            });
+        }
+    }
+
+    fn skip_whitespace(&mut self, newlines: bool) -> Result<(), ParseError> {
+        self.lexer.skip_whitespace(newlines).or_else(|start| self.error(start, "'*/' to close block comment", None))
+    }
+
+    fn error<T>(&self, span: Span, expected: &str, found: Option<char>) -> Result<T, ParseError> {
+        Err(ParseError {
+            file: self.file.clone(),
+            span,
+            expected: expected.to_owned(),
+            found: describe(found)
+        })
+    }
+
+    pub(crate) fn parse_document<'a>(mut self) -> Result<BluefoxData<'a>, ParseError> {
+        self.document(None)
+    }
+
+    // also returns the location of every value, used for editing the source in place
+    pub(crate) fn parse_document_with_nodes<'a>(mut self) -> Result<(BluefoxData<'a>, Vec<Node>), ParseError> {
+        self.nodes = Some(vec![]);
+        let data = self.document(None)?;
+        Ok((data, self.nodes.unwrap_or_default()))
+    }
+
+    // also returns the `@include "..."` directives at the top level and where they are,
+    // without this they are a syntax error
+    pub(crate) fn parse_document_with_includes<'a>(mut self) -> Result<(BluefoxData<'a>, Vec<(String, Span)>), ParseError> {
+        self.includes = Some(vec![]);
+        let data = self.document(None)?;
+        Ok((data, self.includes.unwrap_or_default()))
+    }
+
+    pub(crate) fn parse_value<'a>(mut self) -> Result<BluefoxDataType<'a>, ParseError> {
+        self.skip_whitespace(true)?;
+        let value = self.value(None)?;
+        self.skip_whitespace(true)?;
+        if self.lexer.peek().is_some() {
+            return self.error(self.lexer.span(), "end of input", self.lexer.peek());
+        }
+        Ok(value)
+    }
+
+    fn document<'a>(&mut self, closer: Option<char>) -> Result<BluefoxData<'a>, ParseError> {
+        let mut output = BluefoxData::new();
+        loop {
+            self.skip_whitespace(true)?;
+            match self.lexer.peek() {
+                None if closer.is_none() => break,
+                None => return self.error(self.lexer.span(), "'}'", None),
+                Some(c) if Some(c) == closer => {
+                    self.lexer.bump();
+                    break;
+                },
+                _ => {}
+            }
+
+            if closer.is_none() && self.at_include() {
+                self.include()?;
+                continue;
+            }
+
+            let start = self.lexer.pos;
+            let key = self.key()?;
+            self.skip_whitespace(false)?;
+            self.path.push(PathSegment::Key(key.clone()));
+            let value_start = self.lexer.pos;
+            let value = self.value(closer)?;
+            self.record(start, value_start);
+            self.path.pop();
+            output.data.insert(key, value);
+            self.end_of_item(closer)?;
+        }
+        Ok(output)
+    }
+
+    // `@include: x` is still an ordinary key
+    fn at_include(&self) -> bool {
+        self.includes.is_some() && self.lexer.at("@include") && self.lexer.src[self.lexer.pos + 8..].starts_with([' ', '\t', '"'])
+    }
+
+    fn include(&mut self) -> Result<(), ParseError> {
+        let start = self.lexer.span();
+        for _ in 0..8 {
+            self.lexer.bump();
+        }
+        self.skip_whitespace(false)?;
+        let quote = self.lexer.span();
+        match self.lexer.bump() {
+            Some('"') => {},
+            c => return self.error(quote, "quoted path after @include", c)
+        }
+        let path = self.quoted(quote)?;
+        if let Some(includes) = &mut self.includes {
+            includes.push((path, start));
+        }
+        self.end_of_item(None)
+    }
+
+    fn key(&mut self) -> Result<String, ParseError> {
+        let start = self.lexer.span();
+        let mut key = "".to_owned();
+        loop {
+            match self.lexer.peek() {
+                Some(':') => break,
+                Some(c) if !"\n{}[]\"`".contains(c) => {
+                    key.push(c);
+                    self.lexer.bump();
+                },
+                c => {
+                    return self.error(self.lexer.span(), &format!("':' after key \"{}\"", key.trim()), c);
+                }
+            }
+        }
+        let key = key.trim().to_owned();
+        if key.is_empty() {
+            return self.error(start, "key", Some(':'));
+        }
+        self.lexer.bump();
+        Ok(key)
+    }
+
+    // after a value only whitespace may follow before the next line or the enclosing closer,
+    // inside [] and {} items can also be separated with commas
+    fn end_of_item(&mut self, closer: Option<char>) -> Result<(), ParseError> {
+        self.skip_whitespace(false)?;
+        match (self.lexer.peek(), closer) {
+            (None, _) | (Some('\n'), _) => Ok(()),
+            (Some(c), Some(closer)) if c == closer => Ok(()),
+            (Some(','), Some(_)) => {
+                self.lexer.bump();
+                Ok(())
+            },
+            (c, Some(closer)) => self.error(self.lexer.span(), &format!("',', newline or '{}'", closer), c),
+            (c, None) => self.error(self.lexer.span(), "newline", c)
+        }
+    }
+
+    fn value<'a>(&mut self, closer: Option<char>) -> Result<BluefoxDataType<'a>, ParseError> {
+        let start = self.lexer.span();
+        let value = match self.lexer.peek() {
+            None | Some('\n') => return self.error(start, "value", self.lexer.peek()),
+            Some(c) if Some(c) == closer || (c == ',' && closer.is_some()) => return self.error(start, "value", Some(c)),
+            Some('"') => {
+                self.lexer.bump();
+                BluefoxDataType::STRING(self.quoted(start)?)
+            },
+            Some('\'') if self.lexer.at("'''") => {
+                for _ in 0..3 {
+                    self.lexer.bump();
+                }
+                BluefoxDataType::STRING(self.raw_block(start)?)
+            },
+            Some('\'') => {
+                self.lexer.bump();
+                BluefoxDataType::STRING(self.literal(start)?)
+            },
+            Some('`') => {
+                self.lexer.bump();
+                BluefoxDataType::FUNCTION(self.function(start)?, None)
+            },
+            Some('b') if self.lexer.at("b64\"") => {
+                for _ in 0..4 {
+                    self.lexer.bump();
+                }
+                BluefoxDataType::BYTES(self.bytes(start)?)
+            },
+            Some('[') => {
+                self.lexer.bump();
+                self.array()?
+            },
+            Some('{') => {
+                self.lexer.bump();
+                BluefoxDataType::DATA(self.document(Some('}'))?)
+            },
+            Some(_) => return self.bare(start, closer)
+        };
+        self.value_end = self.lexer.pos;
+        Ok(value)
+    }
+
+    // double quoted strings support \" \\ \n \t \r \0 and \u{...} escapes
+    fn quoted(&mut self, start: Span) -> Result<String, ParseError> {
+        let mut output = "".to_owned();
+        loop {
+            let escape = self.lexer.span();
+            match self.lexer.bump() {
+                None => return self.error(start, "closing '\"' for string", None),
+                Some('"') => return Ok(output),
+                Some('\\') => output.push(self.escape(escape)?),
+                Some(c) => output.push(c)
+            }
+        }
+    }
+
+    fn escape(&mut self, start: Span) -> Result<char, ParseError> {
+        Ok(match self.lexer.bump() {
+            Some('"') => '"',
+            Some('\'') => '\'',
+            Some('\\') => '\\',
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            Some('u') => {
+                if self.lexer.bump() != Some('{') {
+                    return self.error(start, "'{' after \\u", self.lexer.peek());
+                }
+                let mut hex = "".to_owned();
+                loop {
+                    match self.lexer.bump() {
+                        Some('}') => break,
+                        Some(c) if c.is_ascii_hexdigit() && hex.len() < 6 => hex.push(c),
+                        c => return self.error(start, "hex digits and '}' in unicode escape", c)
+                    }
+                }
+                match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    Some(c) => c,
+                    None => return self.error(start, "a valid unicode scalar value", None)
+                }
+            },
+            c => return self.error(start, "escape sequence after '\\'", c)
+        })
+    }
+
+    // single quoted strings are taken as written, without escapes, and end on the same line
+    fn literal(&mut self, start: Span) -> Result<String, ParseError> {
+        let mut output = "".to_owned();
+        loop {
+            match self.lexer.peek() {
+                None | Some('\n') => return self.error(start, "closing \"'\" for string", self.lexer.peek()),
+                Some('\'') => {
+                    self.lexer.bump();
+                    return Ok(output);
+                },
+                Some(c) => {
+                    self.lexer.bump();
+                    output.push(c);
+                }
+            }
+        }
+    }
+
+    // ''' blocks are raw and may span lines, a line break right after the opening quotes is dropped
+    // and the indentation shared by every line, including the line of the closing quotes, is removed
+    fn raw_block(&mut self, start: Span) -> Result<String, ParseError> {
+        let mut text = "".to_owned();
+        while !self.lexer.at("'''") {
+            match self.lexer.bump() {
+                Some(c) => text.push(c),
+                None => return self.error(start, "closing \"'''\" for string", None)
+            }
+        }
+        for _ in 0..3 {
+            self.lexer.bump();
+        }
+        Ok(dedent(&text))
+    }
+
+    // b64"..." holds binary data as base64, the text may be wrapped over several lines
+    fn bytes(&mut self, start: Span) -> Result<Vec<u8>, ParseError> {
+        let mut text = "".to_owned();
+        loop {
+            match self.lexer.bump() {
+                None => return self.error(start, "closing '\"' for base64 data", None),
+                Some('"') => break,
+                Some(c) => text.push(c)
+            }
+        }
+        base64::decode(&text).map_err(|expected| ParseError {
+            file: self.file.clone(),
+            span: start,
+            expected: expected.to_owned(),
+            found: format!("\"{}\"", text.trim())
+        })
+    }
+
+    // function bodies are lua source, so backslashes are only special right before a backtick:
+    // \` is a backtick and \\ a single backslash, any other backslash is kept as written
+    fn function(&mut self, start: Span) -> Result<String, ParseError> {
+        let mut output = "".to_owned();
+        loop {
+            match self.lexer.bump() {
+                None => return self.error(start, "closing '`' for function", None),
+                Some('`') => return Ok(output),
+                Some('\\') => {
+                    let mut run = 1;
+                    while self.lexer.peek() == Some('\\') {
+                        self.lexer.bump();
+                        run += 1;
+                    }
+                    if self.lexer.peek() == Some('`') {
+                        output += &"\\".repeat(run / 2);
+                        if run % 2 == 1 {
+                            self.lexer.bump();
+                            output.push('`');
+                        }
+                    }
+                    else {
+                        output += &"\\".repeat(run);
+                    }
+                },
+                Some(c) => output.push(c)
+            }
+        }
+    }
+
+    fn array<'a>(&mut self) -> Result<BluefoxDataType<'a>, ParseError> {
+        let mut output = vec![];
+        loop {
+            self.skip_whitespace(true)?;
+            match self.lexer.peek() {
+                None => return self.error(self.lexer.span(), "']'", None),
+                Some(']') => {
+                    self.lexer.bump();
+                    break;
+                },
+                _ => {}
+            }
+            self.path.push(PathSegment::Index(output.len()));
+            let value_start = self.lexer.pos;
+            output.push(self.value(Some(']'))?);
+            self.record(value_start, value_start);
+            self.path.pop();
+            self.end_of_item(Some(']'))?;
+        }
+        Ok(BluefoxDataType::ARRAY(output))
+    }
+
+    // unquoted values run until the end of the line, or until a comment that follows whitespace,
+    // inside [] and {} they also end at a comma
+    fn bare<'a>(&mut self, start: Span, closer: Option<char>) -> Result<BluefoxDataType<'a>, ParseError> {
+        let mut text = "".to_owned();
+        while let Some(c) = self.lexer.peek() {
+            if c == '\n' || Some(c) == closer || (c == ',' && closer.is_some()) {
+                break;
+            }
+            if (text.is_empty() || text.ends_with(char::is_whitespace)) && self.lexer.at_comment() {
+                break;
+            }
+            text.push(c);
+            self.lexer.bump();
+            if !c.is_whitespace() {
+                self.value_end = self.lexer.pos;
+            }
+        }
+        let text = text.trim();
+        let value = number(text)
+            .or_else(|| time::parse_datetime(text).map(|x| x.map(BluefoxDataType::DATETIME)))
+            .or_else(|| time::parse_date(text).map(|x| x.map(BluefoxDataType::DATE)))
+            .or_else(|| time::parse_time(text).map(|x| x.map(BluefoxDataType::TIME)))
+            .or_else(|| time::parse_duration(text).map(|x| x.map(BluefoxDataType::DURATION)));
+        match value {
+            Some(Ok(x)) => Ok(x),
+            Some(Err(expected)) => Err(ParseError {
+                file: self.file.clone(),
+                span: start,
+                expected: expected.to_owned(),
+                found: format!("\"{}\"", text)
+            }),
+            None => Ok(scalar(text.to_owned()))
+        }
+    }
+}
+
+fn dedent(text: &str) -> String {
+    let text = match text.find('\n') {
+        Some(i) if text[..i].trim().is_empty() => &text[i + 1..],
+        _ => text
+    };
+    let mut lines: Vec<&str> = text.split('\n').collect();
+    if lines.len() == 1 {
+        return text.to_owned();
+    }
+    let last = lines.len() - 1;
+    let closing_line = lines[last].trim().is_empty();
+
+    let indent_of = |line: &str| line.len() - line.trim_start_matches([' ', '\t']).len();
+    let mut indent = usize::MAX;
+    for (i, line) in lines.iter().enumerate() {
+        if !line.trim().is_empty() || (closing_line && i == last) {
+            indent = indent.min(indent_of(line));
+        }
+    }
+    if closing_line {
+        lines[last] = "";
+    }
+    let lines: Vec<&str> = lines.into_iter().map(|line| &line[indent.min(indent_of(line))..]).collect();
+    lines.join("\n")
+}
+
+// digits of the given radix with single underscores allowed between them, returned without the underscores
+fn digits(text: &str, radix: u32) -> Option<String> {
+    if text.is_empty() || text.starts_with('_') || text.ends_with('_') || text.contains("__") {
+        return None;
+    }
+    if !text.chars().all(|c| c == '_' || c.is_digit(radix)) {
+        return None;
+    }
+    Some(text.replace('_', ""))
+}
+
+// numbers are integers in decimal or with a 0x, 0o or 0b prefix, decimal floats with a fraction and/or
+// exponent, or inf and nan, all with an optional sign. text that doesn't fit the grammar is not a number,
+// text that does but can't be represented is an error
+fn number<'a>(text: &str) -> Option<Result<BluefoxDataType<'a>, &'static str>> {
+    let (negative, unsigned) = match text.strip_prefix('-') {
+        Some(x) => (true, x),
+        None => (false, text.strip_prefix('+').unwrap_or(text))
+    };
+    let signed = |x: f64| if negative { -x } else { x };
+    match unsigned {
+        "inf" => return Some(Ok(BluefoxDataType::FLOAT(signed(f64::INFINITY)))),
+        "nan" => return Some(Ok(BluefoxDataType::FLOAT(signed(f64::NAN)))),
+        _ => {}
+    }
+
+    let (radix, body) = if let Some(x) = unsigned.strip_prefix("0x") {
+        (16, x)
+    }
+    else if let Some(x) = unsigned.strip_prefix("0o") {
+        (8, x)
+    }
+    else if let Some(x) = unsigned.strip_prefix("0b") {
+        (2, x)
+    }
+    else {
+        (10, unsigned)
+    };
+
+    if let Some(clean) = digits(body, radix) {
+        let limit = if negative { i64::MAX as u128 + 1 } else { i64::MAX as u128 };
+        return Some(match u128::from_str_radix(&clean, radix) {
+            Ok(x) if x <= limit => Ok(BluefoxDataType::INT(if negative { -(x as i128) as i64 } else { x as i64 })),
+            _ => Err("integer in the range of a 64 bit signed integer")
+        });
+    }
+    if radix != 10 {
+        return None;
+    }
+
+    let (mantissa, exponent) = match body.find(['e', 'E']) {
+        Some(i) => (&body[..i], Some(&body[i + 1..])),
+        None => (body, None)
+    };
+    let (whole, fraction) = match mantissa.split_once('.') {
+        Some((w, f)) => (w, Some(f)),
+        None => (mantissa, None)
+    };
+    let mut clean = digits(whole, 10)?;
+    if let Some(f) = fraction {
+        clean += ".";
+        clean += &digits(f, 10)?;
+    }
+    if let Some(e) = exponent {
+        let (e_sign, e_digits) = match e.strip_prefix('-') {
+            Some(x) => ("-", x),
+            None => ("", e.strip_prefix('+').unwrap_or(e))
+        };
+        clean += "e";
+        clean += e_sign;
+        clean += &digits(e_digits, 10)?;
+    }
+    else if fraction.is_none() {
+        return None;
+    }
+    match clean.parse::<f64>() {
+        Ok(x) if x.is_finite() => Some(Ok(BluefoxDataType::FLOAT(signed(x)))),
+        _ => Some(Err("float in the range of a 64 bit float"))
+    }
+}
+
+fn scalar<'a>(item: String) -> BluefoxDataType<'a> {
+    if item == "null" {
+        BluefoxDataType::NULL
+    }
+    else if item == "false" {
+        BluefoxDataType::BOOL(false)
+    }
+    else if item == "true" {
+        BluefoxDataType::BOOL(true)
+    }
+    else {
+        BluefoxDataType::STRING(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_location() {
+        let test = "
+        some_int: 4
+        some_data: {
+            more_bool true
+        }";
+
+        let err = Parser::new(test, Some("test.bfn".to_owned())).parse_document().unwrap_err();
+        assert_eq!(err.span, Span { line: 4, column: 27 });
+        assert_eq!(err.to_string(), "test.bfn:4:27: expected ':' after key \"more_bool true\", found end of line");
+
+        let err = Parser::new("some_array: [\n    5\n", None).parse_document().unwrap_err();
+        assert_eq!(err.to_string(), "<string>:3:1: expected ']', found end of input");
+
+        let err = Parser::new("some_quote: \"never closed", None).parse_document().unwrap_err();
+        assert_eq!(err.span, Span { line: 1, column: 13 });
+    }
+
+    #[test]
+    fn inline_containers() {
+        let data = Parser::new("a: [5]\nb: {c: 1}\nurl: http://localhost:80", None).parse_document().unwrap();
+        assert_eq!(data.data["a"], BluefoxDataType::ARRAY(vec![BluefoxDataType::INT(5)]));
+        if let BluefoxDataType::DATA(b) = &data.data["b"] {
+            assert_eq!(b.data["c"], BluefoxDataType::INT(1));
+        }
+        else {
+            assert_eq!("b", "is not recognized as data");
+        }
+        assert_eq!(data.data["url"], BluefoxDataType::STRING("http://localhost:80".to_owned()));
+    }
+
+    #[test]
+    fn comments() {
+        let test = "
+        # leading comment
+        some_int: 4 # trailing comment
+        some_string: hello world // also a comment
+        /* a block
+           comment */
+        some_url: http://localhost:80
+        some_quote: \"# not a comment\"
+        some_function: `return 1 -- # not a comment either`
+        some_array: [
+            5 /* five */
+            // six is skipped
+            7
+        ]";
+
+        let data = Parser::new(test, None).parse_document().unwrap();
+        assert_eq!(data.data["some_int"], BluefoxDataType::INT(4));
+        assert_eq!(data.data["some_string"], BluefoxDataType::STRING("hello world".to_owned()));
+        assert_eq!(data.data["some_url"], BluefoxDataType::STRING("http://localhost:80".to_owned()));
+        assert_eq!(data.data["some_quote"], BluefoxDataType::STRING("# not a comment".to_owned()));
+        assert_eq!(data.data["some_function"], BluefoxDataType::FUNCTION("return 1 -- # not a comment either".to_owned(), None));
+        assert_eq!(data.data["some_array"], BluefoxDataType::ARRAY(vec![BluefoxDataType::INT(5), BluefoxDataType::INT(7)]));
+
+        let err = Parser::new("a: 1\n/* never closed", None).parse_document().unwrap_err();
+        assert_eq!(err.to_string(), "<string>:2:1: expected '*/' to close block comment, found end of input");
+    }
+
+    #[test]
+    fn escapes() {
+        let data = Parser::new(r#"a: "quote \" backslash \\ newline \n tab \t unicode \u{1F98A}"
+b: `return "\`"`"#, None).parse_document().unwrap();
+        assert_eq!(data.data["a"], BluefoxDataType::STRING("quote \" backslash \\ newline \n tab \t unicode \u{1F98A}".to_owned()));
+        assert_eq!(data.data["b"], BluefoxDataType::FUNCTION("return \"`\"".to_owned(), None));
+
+        let err = Parser::new(r#"a: "bad \q""#, None).parse_document().unwrap_err();
+        assert_eq!(err.to_string(), "<string>:1:9: expected escape sequence after '\\', found 'q'");
+        assert!(Parser::new(r#"a: "\u{110000}""#, None).parse_document().is_err());
+    }
+
+    #[test]
+    fn literal_strings() {
+        let test = "
+        some_literal: 'C:\\path\\to \"file\"'
+        some_query: '''
+            SELECT *
+              FROM users
+
+            WHERE id = 1
+            '''
+        some_inline: '''no 'dedent' here'''";
+
+        let data = Parser::new(test, None).parse_document().unwrap();
+        assert_eq!(data.data["some_literal"], BluefoxDataType::STRING("C:\\path\\to \"file\"".to_owned()));
+        assert_eq!(data.data["some_query"], BluefoxDataType::STRING("SELECT *\n  FROM users\n\nWHERE id = 1\n".to_owned()));
+        assert_eq!(data.data["some_inline"], BluefoxDataType::STRING("no 'dedent' here".to_owned()));
+
+        let err = Parser::new("a: 'open\nb: 1", None).parse_document().unwrap_err();
+        assert_eq!(err.to_string(), "<string>:1:4: expected closing \"'\" for string, found end of line");
+    }
+
+    #[test]
+    fn inline_separators() {
+        let test = "
+        some_array: [1, 2.5, \"three, four\", [5, 6], []]
+        some_data: { a: 1, b: [x, y], c: { d: true }, }
+        mixed: [
+            1, 2
+            3,
+        ]
+        some_string: commas, outside of brackets, are kept";
+
+        let data = Parser::new(test, None).parse_document().unwrap();
+        assert_eq!(data.data["some_array"], BluefoxDataType::ARRAY(vec![
+            BluefoxDataType::INT(1),
+            BluefoxDataType::FLOAT(2.5),
+            BluefoxDataType::STRING("three, four".to_owned()),
+            BluefoxDataType::ARRAY(vec![BluefoxDataType::INT(5), BluefoxDataType::INT(6)]),
+            BluefoxDataType::ARRAY(vec![])
+        ]));
+        if let BluefoxDataType::DATA(x) = &data.data["some_data"] {
+            assert_eq!(x.data.len(), 3);
+            assert_eq!(x.data["b"], BluefoxDataType::ARRAY(vec![BluefoxDataType::STRING("x".to_owned()), BluefoxDataType::STRING("y".to_owned())]));
+        }
+        else {
+            assert_eq!("some_data", "is not recognized as data");
+        }
+        assert_eq!(data.data["mixed"], BluefoxDataType::ARRAY(vec![BluefoxDataType::INT(1), BluefoxDataType::INT(2), BluefoxDataType::INT(3)]));
+        assert_eq!(data.data["some_string"], BluefoxDataType::STRING("commas, outside of brackets, are kept".to_owned()));
+
+        let err = Parser::new("a: [1,, 2]", None).parse_document().unwrap_err();
+        assert_eq!(err.to_string(), "<string>:1:7: expected value, found ','");
+        let err = Parser::new("a: [\"x\" \"y\"]", None).parse_document().unwrap_err();
+        assert_eq!(err.to_string(), "<string>:1:9: expected ',', newline or ']', found '\"'");
+    }
+
+    #[test]
+    fn numbers() {
+        let parse = |text: &str| Parser::new(text, None).parse_value();
+        assert_eq!(parse("0xFF").unwrap(), BluefoxDataType::INT(255));
+        assert_eq!(parse("-0o17").unwrap(), BluefoxDataType::INT(-15));
+        assert_eq!(parse("0b1010").unwrap(), BluefoxDataType::INT(10));
+        assert_eq!(parse("1_000_000").unwrap(), BluefoxDataType::INT(1_000_000));
+        assert_eq!(parse("+7").unwrap(), BluefoxDataType::INT(7));
+        assert_eq!(parse("-0x8000_0000_0000_0000").unwrap(), BluefoxDataType::INT(i64::MIN));
+        assert_eq!(parse("1e3").unwrap(), BluefoxDataType::FLOAT(1000.0));
+        assert_eq!(parse("-1_000.5E-1").unwrap(), BluefoxDataType::FLOAT(-100.05));
+        assert_eq!(parse("-inf").unwrap(), BluefoxDataType::FLOAT(f64::NEG_INFINITY));
+        if let BluefoxDataType::FLOAT(x) = parse("nan").unwrap() {
+            assert!(x.is_nan());
+        }
+        else {
+            assert_eq!("nan", "is not recognized as a float");
+        }
+
+        // not numbers by the grammar, so they stay strings
+        for text in ["1.2.3", "1__0", "_1", "0xZZ", ".5", "5.", "NaN", "infinity", "10 apples"] {
+            assert_eq!(parse(text).unwrap(), BluefoxDataType::STRING(text.to_owned()));
+        }
+
+        let err = parse("9223372036854775808").unwrap_err();
+        assert_eq!(err.to_string(), "<string>:1:1: expected integer in the range of a 64 bit signed integer, found \"9223372036854775808\"");
+        assert!(parse("0x1_0000_0000_0000_0000").is_err());
+        assert!(parse("1e400").is_err());
+    }
+}
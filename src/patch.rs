@@ -0,0 +1,254 @@
+use super::{
+    BluefoxData, BluefoxDataType, BluefoxDeserialize, BluefoxError, BluefoxSerialize, FromBluefoxValue, IntoBluefoxValue,
+    PathSegment, parse_path, path_to_string
+};
+
+// one step of a patch, paths are written the same way get_path takes them
+#[derive(Clone, Debug, PartialEq)]
+pub enum PatchOp<'a> {
+    // sets a key that didn't exist, or inserts into an array
+    Add { path: String, value: BluefoxDataType<'a> },
+    Remove { path: String },
+    // from is the value that is expected to be there before the change
+    Change { path: String, from: BluefoxDataType<'a>, value: BluefoxDataType<'a> }
+}
+
+// a patch is written in notation as a list of operations under the key `patch`:
+// patch: [
+//     { op: "change", path: "port", from: 8080, value: 9090 }
+//     { op: "remove", path: "tags[1]" }
+// ]
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Patch<'a> {
+    pub ops: Vec<PatchOp<'a>>
+}
+
+// functions are compared by their source, the compiled function doesn't matter, and floats by
+// their bits so a nan is the same as itself
+fn same(a: &BluefoxDataType, b: &BluefoxDataType) -> bool {
+    match (a, b) {
+        (BluefoxDataType::FUNCTION(a, _), BluefoxDataType::FUNCTION(b, _)) => a == b,
+        (BluefoxDataType::FLOAT(a), BluefoxDataType::FLOAT(b)) => a.to_bits() == b.to_bits(),
+        (BluefoxDataType::ARRAY(a), BluefoxDataType::ARRAY(b)) => a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same(a, b)),
+        (BluefoxDataType::DATA(a), BluefoxDataType::DATA(b)) => {
+            a.data.len() == b.data.len() && a.data.iter().all(|(k, v)| b.data.get(k).is_some_and(|x| same(v, x)))
+        },
+        (a, b) => a == b
+    }
+}
+
+fn diff_data<'a>(old: &BluefoxData<'a>, new: &BluefoxData<'a>, path: &mut Vec<PathSegment>, ops: &mut Vec<PatchOp<'a>>) {
+    for k in old.data.keys() {
+        if !new.data.contains_key(k) {
+            path.push(PathSegment::Key(k.clone()));
+            ops.push(PatchOp::Remove { path: path_to_string(path) });
+            path.pop();
+        }
+    }
+    for (k, v) in &new.data {
+        path.push(PathSegment::Key(k.clone()));
+        match old.data.get(k) {
+            Some(x) => diff_value(x, v, path, ops),
+            None => ops.push(PatchOp::Add { path: path_to_string(path), value: v.clone() })
+        }
+        path.pop();
+    }
+}
+
+// arrays are compared item by item, extra old items are removed from the end first so earlier indices stay valid
+fn diff_value<'a>(old: &BluefoxDataType<'a>, new: &BluefoxDataType<'a>, path: &mut Vec<PathSegment>, ops: &mut Vec<PatchOp<'a>>) {
+    match (old, new) {
+        (BluefoxDataType::DATA(a), BluefoxDataType::DATA(b)) => diff_data(a, b, path, ops),
+        (BluefoxDataType::ARRAY(a), BluefoxDataType::ARRAY(b)) => {
+            for i in (b.len()..a.len()).rev() {
+                path.push(PathSegment::Index(i));
+                ops.push(PatchOp::Remove { path: path_to_string(path) });
+                path.pop();
+            }
+            for (i, v) in b.iter().enumerate() {
+                path.push(PathSegment::Index(i));
+                match a.get(i) {
+                    Some(x) => diff_value(x, v, path, ops),
+                    None => ops.push(PatchOp::Add { path: path_to_string(path), value: v.clone() })
+                }
+                path.pop();
+            }
+        },
+        (a, b) if !same(a, b) => ops.push(PatchOp::Change { path: path_to_string(path), from: a.clone(), value: b.clone() }),
+        _ => {}
+    }
+}
+
+impl <'a> BluefoxData<'a> {
+    // the operations that turn self into other
+    pub fn diff(&self, other: &BluefoxData<'a>) -> Patch<'a> {
+        let mut ops = vec![];
+        diff_data(self, other, &mut vec![], &mut ops);
+        Patch { ops }
+    }
+
+    // applies every operation in order, if any of them fails self is left as it was
+    pub fn apply_patch(&mut self, patch: &Patch<'a>) -> Result<(), BluefoxError> {
+        let mut output = self.clone();
+        for op in &patch.ops {
+            output.apply_op(op)?;
+        }
+        *self = output;
+        Ok(())
+    }
+
+    fn apply_op(&mut self, op: &PatchOp<'a>) -> Result<(), BluefoxError> {
+        match op {
+            PatchOp::Add { path, value } => {
+                let segments = parse_path(path)?;
+                if let (PathSegment::Index(i), parent) = segments.split_last().unwrap() {
+                    if !parent.is_empty() {
+                        if let Some(BluefoxDataType::ARRAY(x)) = self.get_path_mut(&path_to_string(parent))? {
+                            if *i > x.len() {
+                                return Err(BluefoxError::missing_key(path));
+                            }
+                            x.insert(*i, value.clone());
+                            return Ok(());
+                        }
+                    }
+                }
+                self.set_path(path, value.clone())
+            },
+            PatchOp::Remove { path } => {
+                match self.remove_path(path)? {
+                    Some(_) => Ok(()),
+                    None => Err(BluefoxError::missing_key(path))
+                }
+            },
+            PatchOp::Change { path, from, value } => {
+                match self.get_path_mut(path)? {
+                    Some(x) if same(x, from) => {
+                        *x = value.clone();
+                        Ok(())
+                    },
+                    Some(x) => Err(BluefoxError::PatchConflict { path: path.clone(), expected: from.to_string(), found: x.to_string() }),
+                    None => Err(BluefoxError::missing_key(path))
+                }
+            }
+        }
+    }
+}
+
+impl <'a> PatchOp<'a> {
+    fn into_data(self) -> BluefoxData<'a> {
+        let mut output = BluefoxData::new();
+        let (op, path, from, value) = match self {
+            PatchOp::Add { path, value } => ("add", path, None, Some(value)),
+            PatchOp::Remove { path } => ("remove", path, None, None),
+            PatchOp::Change { path, from, value } => ("change", path, Some(from), Some(value))
+        };
+        output.data.insert("op".to_owned(), BluefoxDataType::STRING(op.to_owned()));
+        output.data.insert("path".to_owned(), BluefoxDataType::STRING(path));
+        if let Some(x) = from {
+            output.data.insert("from".to_owned(), x);
+        }
+        if let Some(x) = value {
+            output.data.insert("value".to_owned(), x);
+        }
+        output
+    }
+}
+impl <'a> IntoBluefoxValue<'a> for PatchOp<'a> {
+    fn into_value(self) -> Result<BluefoxDataType<'a>, BluefoxError> {
+        Ok(BluefoxDataType::DATA(self.into_data()))
+    }
+}
+impl <'a> FromBluefoxValue<'a> for PatchOp<'a> {
+    fn from_value(value: BluefoxDataType<'a>) -> Result<Self, BluefoxError> {
+        let mut data = BluefoxData::from_value(value)?;
+        let mut take = |key: &str| data.data.shift_remove(key).ok_or_else(|| BluefoxError::missing_key(key));
+        let op = String::from_value(take("op")?).map_err(|e| e.context("op"))?;
+        let path = String::from_value(take("path")?).map_err(|e| e.context("path"))?;
+        Ok(match &op[..] {
+            "add" => PatchOp::Add { path, value: take("value")? },
+            "remove" => PatchOp::Remove { path },
+            "change" => PatchOp::Change { path, from: take("from")?, value: take("value")? },
+            _ => return Err(BluefoxError::type_mismatch("op", "add, remove or change", &format!("\"{}\"", op)))
+        })
+    }
+}
+
+impl <'a> BluefoxSerialize<'a> for Patch<'a> {
+    fn to_data(self) -> BluefoxData<'a> {
+        let mut output = BluefoxData::new();
+        output.data.insert("patch".to_owned(), BluefoxDataType::ARRAY(self.ops.into_iter().map(|x| BluefoxDataType::DATA(x.into_data())).collect()));
+        output
+    }
+}
+impl <'a> BluefoxDeserialize<'a> for Patch<'a> {
+    fn from_data(data: BluefoxData<'a>) -> Result<Self, BluefoxError> {
+        Ok(Self {
+            ops: data.get_as("patch")?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_and_patch() {
+        let old = BluefoxData::try_from("host: localhost\nport: 8080\ntags: [a, b, c]\ntls: { enabled: false }\nrun: `return 1`").unwrap();
+        let new = BluefoxData::try_from("port: 9090\ntags: [a, d]\ntls: { enabled: false, cert: x }\nrun: `return 1`\ndebug: true").unwrap();
+
+        let patch = old.diff(&new);
+        assert_eq!(patch.ops, vec![
+            PatchOp::Remove { path: "host".to_owned() },
+            PatchOp::Change { path: "port".to_owned(), from: BluefoxDataType::INT(8080), value: BluefoxDataType::INT(9090) },
+            PatchOp::Remove { path: "tags[2]".to_owned() },
+            PatchOp::Change { path: "tags[1]".to_owned(), from: BluefoxDataType::STRING("b".to_owned()), value: BluefoxDataType::STRING("d".to_owned()) },
+            PatchOp::Add { path: "tls.cert".to_owned(), value: BluefoxDataType::STRING("x".to_owned()) },
+            PatchOp::Add { path: "debug".to_owned(), value: BluefoxDataType::BOOL(true) }
+        ]);
+        assert!(old.diff(&old).ops.is_empty());
+
+        let text = BluefoxData::to_string(patch.clone());
+        assert!(text.starts_with("patch: [\n    { op: \"remove\", path: \"host\" }\n"));
+        let read = Patch::from_data(BluefoxData::try_from(text).unwrap()).unwrap();
+        assert_eq!(read, patch);
+
+        let mut patched = old.clone();
+        patched.apply_patch(&read).unwrap();
+        assert!(patched.diff(&new).ops.is_empty());
+
+        // the same patch can't be applied twice, and a failed patch changes nothing
+        match patched.apply_patch(&read) {
+            Err(BluefoxError::MissingKey { path }) => assert_eq!(path, "host"),
+            other => panic!("expected missing key, got {:?}", other)
+        }
+        let mut changed = old.clone();
+        changed.set_path("port", BluefoxDataType::INT(1)).unwrap();
+        let before = changed.clone();
+        match changed.apply_patch(&Patch { ops: patch.ops[1..].to_vec() }) {
+            Err(BluefoxError::PatchConflict { path, expected, found }) => assert_eq!((path.as_str(), expected.as_str(), found.as_str()), ("port", "8080", "1")),
+            other => panic!("expected patch conflict, got {:?}", other)
+        }
+        assert_eq!(changed, before);
+
+        let mut data = BluefoxData::try_from("tags: [a, c]").unwrap();
+        data.apply_patch(&Patch { ops: vec![PatchOp::Add { path: "tags[1]".to_owned(), value: BluefoxDataType::STRING("b".to_owned()) }] }).unwrap();
+        assert_eq!(data.to_string(), "tags: [\"a\", \"b\", \"c\"]\n");
+
+        // a key with a dot in it isn't taken for a path into the data next to it
+        let old = BluefoxData::try_from("a.b: 1\na: { b: 5 }").unwrap();
+        let new = BluefoxData::try_from("a.b: 2\na: { b: 5 }").unwrap();
+        let patch = old.diff(&new);
+        assert_eq!(patch.ops, vec![PatchOp::Change { path: "[\"a.b\"]".to_owned(), from: BluefoxDataType::INT(1), value: BluefoxDataType::INT(2) }]);
+        let read = Patch::from_data(BluefoxData::try_from(BluefoxData::to_string(patch)).unwrap()).unwrap();
+        let mut patched = old.clone();
+        patched.apply_patch(&read).unwrap();
+        assert_eq!(patched, new);
+
+        let data = BluefoxData::try_from("x: nan\ny: [nan]").unwrap();
+        assert!(data.diff(&data).ops.is_empty());
+        let mut patched = data.clone();
+        patched.apply_patch(&data.diff(&BluefoxData::try_from("x: 1.0\ny: [nan]").unwrap())).unwrap();
+        assert_eq!(patched.data["x"], BluefoxDataType::FLOAT(1.0));
+    }
+}
@@ -0,0 +1,310 @@
+use std::fmt;
+
+use super::{BluefoxData, BluefoxDataType, BluefoxError, FromBluefoxValue};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize)
+}
+
+// parses paths of the form `a.b[2].c`, the same form execute takes, a key that can't be
+// written bare is quoted in brackets, as in `a["b.c"]`, with `\"` and `\\` escaped
+pub fn parse_path(path: &str) -> Result<Vec<PathSegment>, BluefoxError> {
+    let invalid = |reason: &str| BluefoxError::InvalidPath { path: path.to_owned(), reason: reason.to_owned() };
+
+    let mut output = vec![];
+    let mut key = "".to_owned();
+    let mut chars = path.chars().peekable();
+    let mut after_index = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => {
+                if key.is_empty() && !after_index {
+                    return Err(invalid("empty key"));
+                }
+                if !key.is_empty() {
+                    output.push(PathSegment::Key(key.clone()));
+                    key.clear();
+                }
+                after_index = false;
+                if chars.peek().is_none() {
+                    return Err(invalid("empty key"));
+                }
+            },
+            '[' => {
+                if !key.is_empty() {
+                    output.push(PathSegment::Key(key.clone()));
+                    key.clear();
+                }
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    let mut quoted = "".to_owned();
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some('\\') => match chars.next() {
+                                Some(c) => quoted.push(c),
+                                None => return Err(invalid("expected '\"'"))
+                            },
+                            Some(c) => quoted.push(c),
+                            None => return Err(invalid("expected '\"'"))
+                        }
+                    }
+                    if chars.next() != Some(']') {
+                        return Err(invalid("expected ']'"));
+                    }
+                    output.push(PathSegment::Key(quoted));
+                }
+                else {
+                    let mut index = "".to_owned();
+                    loop {
+                        match chars.next() {
+                            Some(']') => break,
+                            Some(c) => index.push(c),
+                            None => return Err(invalid("expected ']'"))
+                        }
+                    }
+                    match index.trim().parse::<usize>() {
+                        Ok(i) => output.push(PathSegment::Index(i)),
+                        Err(_) => return Err(invalid(&format!("\"{}\" is not an index", index)))
+                    }
+                }
+                after_index = true;
+            },
+            c => {
+                if after_index {
+                    return Err(invalid("expected '.' or '[' after index"));
+                }
+                key.push(c);
+            }
+        }
+    }
+    if !key.is_empty() {
+        output.push(PathSegment::Key(key));
+    }
+    if output.is_empty() {
+        return Err(invalid("empty path"));
+    }
+    Ok(output)
+}
+
+fn needs_quotes(key: &str) -> bool {
+    key.is_empty() || key.contains(['.', '[', ']', '"', '\\'])
+}
+
+// the inverse of parse_path, every key comes back out as the same segment
+pub fn path_to_string(path: &[PathSegment]) -> String {
+    let mut output = "".to_owned();
+    for segment in path {
+        match segment {
+            PathSegment::Key(x) if !needs_quotes(x) => {
+                if !output.is_empty() {
+                    output.push('.');
+                }
+                output += x;
+            },
+            segment => output += &segment.to_string()
+        }
+    }
+    output
+}
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathSegment::Key(x) if needs_quotes(x) => write!(f, "[\"{}\"]", x.replace('\\', "\\\\").replace('"', "\\\"")),
+            PathSegment::Key(x) => write!(f, "{}", x),
+            PathSegment::Index(i) => write!(f, "[{}]", i)
+        }
+    }
+}
+
+// a missing key in the middle of a path is created as data, or as an array if it is followed by an index
+fn container_for<'a>(next: &PathSegment) -> BluefoxDataType<'a> {
+    match next {
+        PathSegment::Key(_) => BluefoxDataType::DATA(BluefoxData::new()),
+        PathSegment::Index(_) => BluefoxDataType::ARRAY(vec![])
+    }
+}
+
+// errors carry the path relative to target, the caller adds the rest with context
+fn set_in<'a>(target: &mut BluefoxDataType<'a>, segments: &[PathSegment], value: BluefoxDataType<'a>) -> Result<(), BluefoxError> {
+    let segment = &segments[0];
+    let last = segments.len() == 1;
+    let slot = match (target, segment) {
+        (BluefoxDataType::DATA(x), PathSegment::Key(k)) => {
+            if last {
+                x.data.insert(k.clone(), value);
+                return Ok(());
+            }
+            x.data.entry(k.clone()).or_insert_with(|| container_for(&segments[1]))
+        },
+        (BluefoxDataType::ARRAY(x), PathSegment::Index(i)) => {
+            // an index one past the end appends, anything further would leave a gap
+            if *i > x.len() {
+                return Err(BluefoxError::missing_key(&segment.to_string()));
+            }
+            if *i == x.len() {
+                if last {
+                    x.push(value);
+                    return Ok(());
+                }
+                x.push(container_for(&segments[1]));
+            }
+            else if last {
+                x[*i] = value;
+                return Ok(());
+            }
+            &mut x[*i]
+        },
+        (other, PathSegment::Key(_)) => return Err(BluefoxError::type_mismatch("", "data", other.type_name())),
+        (other, PathSegment::Index(_)) => return Err(BluefoxError::type_mismatch("", "array", other.type_name()))
+    };
+    set_in(slot, &segments[1..], value).map_err(|e| e.context(&segment.to_string()))
+}
+
+impl <'a> BluefoxData<'a> {
+    pub fn get_path(&self, path: &str) -> Result<Option<&BluefoxDataType<'a>>, BluefoxError> {
+        Ok(self.find(&parse_path(path)?))
+    }
+
+    pub fn get_path_mut(&mut self, path: &str) -> Result<Option<&mut BluefoxDataType<'a>>, BluefoxError> {
+        Ok(self.find_mut(&parse_path(path)?))
+    }
+
+    // reads the value at path as T, errors say where the value was and what was found there
+    pub fn get_as<T>(&self, path: &str) -> Result<T, BluefoxError> where T: FromBluefoxValue<'a> {
+        let segments = parse_path(path)?;
+        match self.find(&segments) {
+            Some(x) => T::from_value(x.clone()).map_err(|e| e.context(&path_to_string(&segments))),
+            None => Err(BluefoxError::missing_key(&path_to_string(&segments)))
+        }
+    }
+
+    // sets the value at path, creating any data or arrays leading up to it that don't exist yet
+    pub fn set_path(&mut self, path: &str, value: BluefoxDataType<'a>) -> Result<(), BluefoxError> {
+        let segments = parse_path(path)?;
+        let key = match &segments[0] {
+            PathSegment::Key(x) => x,
+            PathSegment::Index(_) => return Err(BluefoxError::type_mismatch("", "array", "data"))
+        };
+        if segments.len() == 1 {
+            self.data.insert(key.clone(), value);
+            return Ok(());
+        }
+        let slot = self.data.entry(key.clone()).or_insert_with(|| container_for(&segments[1]));
+        set_in(slot, &segments[1..], value).map_err(|e| e.context(&segments[0].to_string()))
+    }
+
+    // removes and returns the value at path, later array items move down to fill the gap
+    pub fn remove_path(&mut self, path: &str) -> Result<Option<BluefoxDataType<'a>>, BluefoxError> {
+        let segments = parse_path(path)?;
+        let (last, parent) = segments.split_last().unwrap();
+        if parent.is_empty() {
+            return Ok(match last {
+                PathSegment::Key(k) => self.data.shift_remove(k),
+                PathSegment::Index(_) => None
+            });
+        }
+        Ok(match (self.find_mut(parent), last) {
+            (Some(BluefoxDataType::DATA(x)), PathSegment::Key(k)) => x.data.shift_remove(k),
+            (Some(BluefoxDataType::ARRAY(x)), PathSegment::Index(i)) if *i < x.len() => Some(x.remove(*i)),
+            _ => None
+        })
+    }
+
+    pub(crate) fn find(&self, path: &[PathSegment]) -> Option<&BluefoxDataType<'a>> {
+        let mut current = match &path[0] {
+            PathSegment::Key(k) => self.data.get(k)?,
+            PathSegment::Index(_) => return None
+        };
+        for segment in &path[1..] {
+            current = match (current, segment) {
+                (BluefoxDataType::DATA(x), PathSegment::Key(k)) => x.data.get(k)?,
+                (BluefoxDataType::ARRAY(x), PathSegment::Index(i)) => x.get(*i)?,
+                _ => return None
+            };
+        }
+        Some(current)
+    }
+
+    pub(crate) fn find_mut(&mut self, path: &[PathSegment]) -> Option<&mut BluefoxDataType<'a>> {
+        let mut current = match &path[0] {
+            PathSegment::Key(k) => self.data.get_mut(k)?,
+            PathSegment::Index(_) => return None
+        };
+        for segment in &path[1..] {
+            current = match (current, segment) {
+                (BluefoxDataType::DATA(x), PathSegment::Key(k)) => x.data.get_mut(k)?,
+                (BluefoxDataType::ARRAY(x), PathSegment::Index(i)) => x.get_mut(*i)?,
+                _ => return None
+            };
+        }
+        Some(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths() {
+        let path = parse_path("a.b[2].c").unwrap();
+        assert_eq!(path, vec![
+            PathSegment::Key("a".to_owned()),
+            PathSegment::Key("b".to_owned()),
+            PathSegment::Index(2),
+            PathSegment::Key("c".to_owned())
+        ]);
+        assert_eq!(path_to_string(&path), "a.b[2].c");
+        assert_eq!(parse_path("[0][1]").unwrap(), vec![PathSegment::Index(0), PathSegment::Index(1)]);
+
+        assert!(parse_path("").is_err());
+        assert!(parse_path("a..b").is_err());
+        assert!(parse_path("a.").is_err());
+        assert!(parse_path("a[x]").is_err());
+        assert!(parse_path("a[1").is_err());
+        assert!(parse_path("a[1]b").is_err());
+
+        let path = vec![
+            PathSegment::Key("a.b".to_owned()),
+            PathSegment::Key("".to_owned()),
+            PathSegment::Key("say \"hi\\\"".to_owned()),
+            PathSegment::Index(0),
+            PathSegment::Key("c d".to_owned())
+        ];
+        assert_eq!(path_to_string(&path), "[\"a.b\"][\"\"][\"say \\\"hi\\\\\\\"\"][0].c d");
+        assert_eq!(parse_path(&path_to_string(&path)).unwrap(), path);
+        assert!(parse_path("a[\"b").is_err());
+        assert!(parse_path("a[\"b\"c]").is_err());
+    }
+
+    #[test]
+    fn data_paths() {
+        let mut data = BluefoxData::try_from("a: { b: [1, { c: 2 }] }\nd: 3").unwrap();
+        assert_eq!(data.get_path("a.b[1].c").unwrap(), Some(&BluefoxDataType::INT(2)));
+        assert_eq!(data.get_path("a.b[5]").unwrap(), None);
+        assert_eq!(data.get_path("d.e").unwrap(), None);
+        assert!(data.get_path("a..b").is_err());
+
+        *data.get_path_mut("a.b[0]").unwrap().unwrap() = BluefoxDataType::INT(7);
+        data.set_path("a.b[2]", BluefoxDataType::BOOL(true)).unwrap();
+        data.set_path("x.y[0].z", BluefoxDataType::INT(4)).unwrap();
+        data.set_path("d", BluefoxDataType::NULL).unwrap();
+        assert_eq!(data.to_string(), "a: { b: [7, { c: 2 }, true] }\nd: null\nx: { y: [{ z: 4 }] }\n");
+
+        match data.set_path("a.b[0].c", BluefoxDataType::NULL) {
+            Err(BluefoxError::TypeMismatch { path, found, .. }) => assert_eq!((path.as_str(), found.as_str()), ("a.b[0]", "int")),
+            other => panic!("expected type mismatch, got {:?}", other)
+        }
+        assert!(data.set_path("a.b[9]", BluefoxDataType::NULL).is_err());
+
+        assert_eq!(data.remove_path("a.b[0]").unwrap(), Some(BluefoxDataType::INT(7)));
+        assert_eq!(data.remove_path("a.b[0].c").unwrap(), Some(BluefoxDataType::INT(2)));
+        assert_eq!(data.remove_path("d").unwrap(), Some(BluefoxDataType::NULL));
+        assert_eq!(data.remove_path("missing.key").unwrap(), None);
+        assert_eq!(data.to_string(), "a: { b: [{}, true] }\nx: { y: [{ z: 4 }] }\n");
+    }
+}
@@ -0,0 +1,450 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap
+};
+use mlua::prelude::*;
+
+use super::{BluefoxData, BluefoxDataType, PathSegment, bluefox_table, find_function};
+
+// lua sees the document through proxies, empty tables whose metatable reads from and writes to the
+// rust side, so a call only converts the values it touches and only the keys it sets change
+
+const STASH: &str = "bluefox_notation.proxy";
+
+// scoped callbacks get values tied to their own borrow of the lua state, this moves a value
+// between two borrows of the same state by way of the registry
+fn relife<'x, 'y>(from: &'x Lua, to: &'y Lua, value: LuaValue<'x>) -> LuaResult<LuaValue<'y>> {
+    from.set_named_registry_value(STASH, value)?;
+    let value = to.named_registry_value(STASH)?;
+    to.unset_named_registry_value(STASH)?;
+    Ok(value)
+}
+
+enum Container<'r, 'a> {
+    Data(&'r BluefoxData<'a>),
+    Array(&'r Vec<BluefoxDataType<'a>>)
+}
+
+enum ContainerMut<'r, 'a> {
+    Data(&'r mut BluefoxData<'a>),
+    Array(&'r mut Vec<BluefoxDataType<'a>>)
+}
+
+fn container<'r, 'a>(doc: &'r BluefoxData<'a>, path: &[PathSegment]) -> Option<Container<'r, 'a>> {
+    if path.is_empty() {
+        return Some(Container::Data(doc));
+    }
+    match doc.find(path)? {
+        BluefoxDataType::DATA(x) => Some(Container::Data(x)),
+        BluefoxDataType::ARRAY(x) => Some(Container::Array(x)),
+        _ => None
+    }
+}
+
+fn container_mut<'r, 'a>(doc: &'r mut BluefoxData<'a>, path: &[PathSegment]) -> Option<ContainerMut<'r, 'a>> {
+    if path.is_empty() {
+        return Some(ContainerMut::Data(doc));
+    }
+    match doc.find_mut(path)? {
+        BluefoxDataType::DATA(x) => Some(ContainerMut::Data(x)),
+        BluefoxDataType::ARRAY(x) => Some(ContainerMut::Array(x)),
+        _ => None
+    }
+}
+
+// data takes string keys, arrays take the usual 1 based lua indices
+fn segment(container: &Container, key: &LuaValue) -> Option<PathSegment> {
+    match (container, key) {
+        (Container::Data(_), LuaValue::String(x)) => Some(PathSegment::Key(x.to_str().ok()?.to_owned())),
+        (Container::Data(_), LuaValue::Integer(x)) => Some(PathSegment::Key(x.to_string())),
+        (Container::Array(_), LuaValue::Integer(x)) if *x >= 1 => Some(PathSegment::Index(*x as usize - 1)),
+        (Container::Array(_), LuaValue::Number(x)) if *x >= 1.0 && x.trunc() == *x => Some(PathSegment::Index(*x as usize - 1)),
+        _ => None
+    }
+}
+
+struct View<'s, 'a> {
+    lua: &'a Lua,
+    doc: RefCell<&'s mut BluefoxData<'a>>,
+    env: LuaTable<'a>,
+    metatable: LuaTable<'a>,
+    // one proxy per path, so the same value is the same table every time it's read, a proxy
+    // assigned somewhere else moves along with its value, as table.insert and table.remove do
+    // with the items they shift, and a proxy whose value is overwritten or removed is dropped
+    proxies: RefCell<HashMap<Vec<PathSegment>, LuaTable<'a>>>,
+    paths: RefCell<HashMap<usize, Vec<PathSegment>>>
+}
+
+fn stale() -> LuaError {
+    LuaError::runtime("this value is no longer in the document")
+}
+impl <'s, 'a> View<'s, 'a> {
+    fn proxy(&self, path: Vec<PathSegment>) -> LuaResult<LuaTable<'a>> {
+        if let Some(x) = self.proxies.borrow().get(&path) {
+            return Ok(x.clone());
+        }
+        let table = self.lua.create_table()?;
+        table.set_metatable(Some(self.metatable.clone()));
+        self.paths.borrow_mut().insert(table.to_pointer() as usize, path.clone());
+        self.proxies.borrow_mut().insert(path, table.clone());
+        Ok(table)
+    }
+
+    fn path_of(&self, table: usize) -> Option<Vec<PathSegment>> {
+        self.paths.borrow().get(&table).cloned()
+    }
+
+    fn live_path(&self, table: usize) -> LuaResult<Vec<PathSegment>> {
+        self.path_of(table).ok_or_else(stale)
+    }
+
+    fn is_proxy(&self, table: &LuaTable) -> bool {
+        table.get_metatable().is_some_and(|x| x.to_pointer() == self.metatable.to_pointer())
+    }
+
+    // drops the proxies of path and everything in it, except those under keep
+    fn forget(&self, path: &[PathSegment], keep: Option<&[PathSegment]>) {
+        let mut paths = self.paths.borrow_mut();
+        self.proxies.borrow_mut().retain(|x, table| {
+            let dropped = x.starts_with(path) && !keep.is_some_and(|keep| x.starts_with(keep));
+            if dropped {
+                paths.remove(&(table.to_pointer() as usize));
+            }
+            !dropped
+        });
+    }
+
+    // points the proxies of from and everything in it at the same place under to
+    fn rebase(&self, from: &[PathSegment], to: &[PathSegment]) {
+        let mut proxies = self.proxies.borrow_mut();
+        let moved: Vec<Vec<PathSegment>> = proxies.keys().filter(|x| x.starts_with(from)).cloned().collect();
+        let moved: Vec<(Vec<PathSegment>, LuaTable)> = moved.into_iter().map(|x| {
+            let table = proxies.remove(&x).unwrap();
+            ([to, &x[from.len()..]].concat(), table)
+        }).collect();
+        let mut paths = self.paths.borrow_mut();
+        for (path, table) in moved {
+            paths.insert(table.to_pointer() as usize, path.clone());
+            proxies.insert(path, table);
+        }
+    }
+
+    fn segment(&self, path: &[PathSegment], key: &LuaValue) -> Option<PathSegment> {
+        segment(&container(&self.doc.borrow(), path)?, key)
+    }
+
+    // functions are compiled on first use and kept in the document, and always run in this call's environment
+    fn get(&self, path: &[PathSegment]) -> LuaResult<LuaValue<'a>> {
+        let value = match self.doc.borrow().find(path) {
+            None => return Ok(LuaValue::Nil),
+            Some(BluefoxDataType::DATA(_) | BluefoxDataType::ARRAY(_)) => None,
+            Some(x) => Some(x.clone())
+        };
+        match value {
+            None => Ok(LuaValue::Table(self.proxy(path.to_vec())?)),
+            Some(BluefoxDataType::FUNCTION(x, c)) => {
+                let f = match c {
+                    Some(f) => f,
+                    None => {
+                        let f = self.lua.load(x.clone()).into_function()?;
+                        if let Some(v) = self.doc.borrow_mut().find_mut(path) {
+                            *v = BluefoxDataType::FUNCTION(x, Some(f.clone()));
+                        }
+                        f
+                    }
+                };
+                f.set_environment(self.env.clone())?;
+                Ok(LuaValue::Function(f))
+            },
+            Some(x) => x.into_lua(self.lua)
+        }
+    }
+
+    fn index(&self, table: usize, key: LuaValue<'a>) -> LuaResult<LuaValue<'a>> {
+        let mut path = self.live_path(table)?;
+        match self.segment(&path, &key) {
+            Some(x) => {
+                path.push(x);
+                self.get(&path)
+            },
+            None => Ok(LuaValue::Nil)
+        }
+    }
+
+    // nil removes a key, or the last item of an array, anywhere else in an array it leaves a null
+    fn new_index(&self, table: usize, key: LuaValue<'a>, value: LuaValue<'a>) -> LuaResult<()> {
+        let path = self.live_path(table)?;
+        let segment = self.segment(&path, &key);
+        let moved = match &value {
+            LuaValue::Table(x) => self.path_of(x.to_pointer() as usize),
+            _ => None
+        };
+        let value = match (value, &segment) {
+            (LuaValue::Nil, _) => None,
+            (x, Some(segment)) => {
+                let previous = [path.clone(), vec![segment.clone()]].concat();
+                Some(self.to_value(x, self.doc.borrow().find(&previous))?)
+            },
+            (x, None) => Some(self.to_value(x, None)?)
+        };
+
+        let mut doc = self.doc.borrow_mut();
+        match (container_mut(&mut doc, &path), segment.clone()) {
+            (Some(ContainerMut::Data(x)), Some(PathSegment::Key(k))) => {
+                match value {
+                    Some(v) => {
+                        x.data.insert(k, v);
+                    },
+                    None => {
+                        x.data.shift_remove(&k);
+                    }
+                }
+            },
+            (Some(ContainerMut::Array(x)), Some(PathSegment::Index(i))) if i <= x.len() => {
+                match value {
+                    Some(v) if i == x.len() => x.push(v),
+                    Some(v) => x[i] = v,
+                    None if i + 1 == x.len() => {
+                        x.pop();
+                    },
+                    None if i < x.len() => x[i] = BluefoxDataType::NULL,
+                    None => {}
+                }
+            },
+            (Some(ContainerMut::Array(_)), _) => return Err(LuaError::runtime(format!("{:?} is not an index in this array", key))),
+            _ => return Err(LuaError::runtime(format!("{:?} can't be used as a key", key)))
+        }
+        drop(doc);
+
+        let target = [path, vec![segment.unwrap()]].concat();
+        match moved {
+            Some(from) if from == target => {},
+            // a value put inside itself is a copy, there is nothing to move
+            Some(from) if !target.starts_with(&from) => {
+                self.forget(&target, Some(&from));
+                self.rebase(&from, &target);
+            },
+            _ => self.forget(&target, None)
+        }
+        Ok(())
+    }
+
+    fn len(&self, table: usize) -> LuaResult<usize> {
+        let path = self.live_path(table)?;
+        Ok(match container(&self.doc.borrow(), &path) {
+            Some(Container::Array(x)) => x.len(),
+            _ => 0
+        })
+    }
+
+    // the iterator pairs uses, in document order
+    fn next(&self, table: usize, key: LuaValue<'a>) -> LuaResult<(LuaValue<'a>, LuaValue<'a>)> {
+        let mut path = self.live_path(table)?;
+        let next = match container(&self.doc.borrow(), &path) {
+            Some(Container::Data(x)) => {
+                let i = match &key {
+                    LuaValue::Nil => 0,
+                    key => match segment(&Container::Data(x), key) {
+                        Some(PathSegment::Key(k)) => x.data.get_index_of(&k).map_or(x.data.len(), |i| i + 1),
+                        _ => x.data.len()
+                    }
+                };
+                x.data.get_index(i).map(|(k, _)| (k.clone().into_lua(self.lua), PathSegment::Key(k.clone())))
+            },
+            Some(Container::Array(x)) => {
+                let i = match &key {
+                    LuaValue::Nil => 0,
+                    key => match segment(&Container::Array(x), key) {
+                        Some(PathSegment::Index(i)) => i + 1,
+                        _ => x.len()
+                    }
+                };
+                if i < x.len() { Some((Ok(LuaValue::Integer(i as i64 + 1)), PathSegment::Index(i))) } else { None }
+            },
+            None => None
+        };
+        match next {
+            Some((key, segment)) => {
+                path.push(segment);
+                Ok((key?, self.get(&path)?))
+            },
+            None => Ok((LuaValue::Nil, LuaValue::Nil))
+        }
+    }
+
+    // proxies are read back out of the document, anywhere in the value, the keys of new tables
+    // are put in the order of the value they replace
+    fn to_value(&self, value: LuaValue<'a>, previous: Option<&BluefoxDataType>) -> LuaResult<BluefoxDataType<'a>> {
+        if let LuaValue::Table(x) = &value {
+            if let Some(path) = self.path_of(x.to_pointer() as usize) {
+                let doc = self.doc.borrow();
+                return Ok(match path.is_empty() {
+                    true => BluefoxDataType::DATA(doc.clone()),
+                    false => doc.find(&path).cloned().unwrap_or(BluefoxDataType::NULL)
+                });
+            }
+            if self.is_proxy(x) {
+                return Err(stale());
+            }
+            let mut output = BluefoxDataType::from_lua(value.clone(), self.lua)?;
+            output.keep_order(previous);
+            let pairs: Vec<(LuaValue, LuaValue)> = x.clone().pairs().collect::<LuaResult<_>>()?;
+            for (k, v) in pairs {
+                if let LuaValue::Table(_) = v {
+                    let v = self.to_value(v, None)?;
+                    match (&mut output, k) {
+                        (BluefoxDataType::ARRAY(o), LuaValue::Integer(i)) => {
+                            if let Some(x) = usize::try_from(i - 1).ok().and_then(|i| o.get_mut(i)) {
+                                *x = v;
+                            }
+                        },
+                        (BluefoxDataType::DATA(o), k) => {
+                            o.data.insert(String::from_lua(k, self.lua)?, v);
+                        },
+                        _ => {}
+                    }
+                }
+            }
+            return Ok(output);
+        }
+        BluefoxDataType::from_lua(value, self.lua)
+    }
+
+    fn install<'scope>(&'scope self, scope: &mlua::Scope<'a, 'scope>) -> LuaResult<()> where 's: 'scope {
+        self.metatable.set("__index", scope.create_function(move |lua, (table, key): (LuaTable, LuaValue)| {
+            let value = self.index(table.to_pointer() as usize, relife(lua, self.lua, key)?)?;
+            relife(self.lua, lua, value)
+        })?)?;
+        self.metatable.set("__newindex", scope.create_function(move |lua, (table, key, value): (LuaTable, LuaValue, LuaValue)| {
+            self.new_index(table.to_pointer() as usize, relife(lua, self.lua, key)?, relife(lua, self.lua, value)?)
+        })?)?;
+        self.metatable.set("__len", scope.create_function(move |_, table: LuaTable| {
+            self.len(table.to_pointer() as usize)
+        })?)?;
+        let next = scope.create_function(move |lua, (table, key): (LuaTable, LuaValue)| {
+            let (key, value) = self.next(table.to_pointer() as usize, relife(lua, self.lua, key)?)?;
+            Ok((relife(self.lua, lua, key)?, relife(self.lua, lua, value)?))
+        })?;
+        self.metatable.set("__pairs", scope.create_function(move |_, table: LuaTable| {
+            Ok((next.clone(), table, LuaValue::Nil))
+        })?)?;
+        self.metatable.set("__metatable", false)?;
+        Ok(())
+    }
+}
+
+// runs the function at func with the document as `notation` in env, changes are written straight into doc
+pub(crate) fn call<'a, A>(doc: &mut BluefoxData<'a>, lua: &'a Lua, env: LuaTable<'a>, func: &str, args: A) -> LuaResult<BluefoxDataType<'a>> where A: IntoLuaMulti<'a> {
+    let view = View {
+        lua,
+        doc: RefCell::new(doc),
+        env,
+        metatable: lua.create_table()?,
+        proxies: RefCell::new(HashMap::new()),
+        paths: RefCell::new(HashMap::new())
+    };
+    lua.scope(|scope| {
+        view.install(scope)?;
+        let root = view.proxy(vec![])?;
+        view.env.raw_set("notation", root.clone())?;
+        view.env.raw_set("bluefox", bluefox_table(lua)?)?;
+
+        // no need to check if function, lua will error if it isn't
+        let some_function = find_function(root, func)?;
+        let result = some_function.call::<A, LuaValue>(args)?;
+        view.to_value(result, None)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proxies() {
+        let lua = Lua::new();
+        lua.globals().set("notation", "mine").unwrap();
+        lua.globals().set("offset", 10).unwrap();
+        let mut data = BluefoxData::try_from("
+        port: 8080
+        tags: [a, b, c]
+        tls: { enabled: false }
+        empty: null
+        run: `
+            local keys = {}
+            for k, v in pairs(notation) do
+                if type(v) ~= 'function' then keys[#keys + 1] = k end
+            end
+            notation.port = notation.port + offset
+            table.insert(notation.tags, 'd')
+            table.remove(notation.tags, 1)
+            notation.tls.enabled = true
+            notation.tls.cert = { path = 'x.pem', days = 30 }
+            notation.copy = notation.tls
+            notation.tags[#notation.tags] = nil
+            written = true
+            return { keys = table.concat(keys, ','), count = #notation.tags, same = notation.tls == notation.tls }
+        `").unwrap();
+
+        let result = data.execute(&lua, "run".to_owned(), ()).unwrap().to_string();
+        data.data.shift_remove("run");
+        assert_eq!(result, "{ count: 2, keys: \"port,tags,tls,empty\", same: true }");
+        assert_eq!(data.to_string(), "port: 8090
+tags: [\"b\", \"c\"]
+tls: { enabled: true, cert: { days: 30, path: \"x.pem\" } }
+empty: null
+copy: { enabled: true, cert: { days: 30, path: \"x.pem\" } }
+");
+        // the global notation is left alone, other globals can still be read and written
+        assert_eq!(lua.globals().get::<_, String>("notation").unwrap(), "mine");
+        assert!(lua.globals().get::<_, bool>("written").unwrap());
+    }
+
+    #[test]
+    fn moved_proxies() {
+        let lua = Lua::new();
+        let mut data = BluefoxData::try_from("
+        servers: [{ name: a }, { name: b, tls: {} }, { name: c }]
+        run: `
+            local b, c, tls = notation.servers[2], notation.servers[3], notation.servers[2].tls
+            table.remove(notation.servers, 1)
+            b.port = 1
+            c.port = 2
+            table.insert(notation.servers, 1, { name = 'z' })
+            b.weight = 3
+            tls.enabled = true
+            notation.servers[3] = nil
+            local ok, e = pcall(function() c.port = 9 end)
+            return tostring(e)
+        `").unwrap();
+
+        let result = data.execute(&lua, "run".to_owned(), ()).unwrap().to_string();
+        assert!(result.contains("this value is no longer in the document"));
+        let expected = BluefoxData::try_from("servers: [{ name: z }, { name: b, tls: { enabled: true }, port: 1, weight: 3 }]").unwrap();
+        assert_eq!(data.data["servers"], expected.data["servers"]);
+    }
+
+    #[test]
+    fn holes() {
+        let lua = Lua::new();
+        let mut data = BluefoxData::try_from("
+        run: `
+            notation.list = {1, nil, {}}
+            notation.zero = {[0] = {}}
+            notation.negative = {[-1] = {}}
+            notation.sparse = {[1000000] = {}}
+            return {1, nil, {}}
+        `").unwrap();
+
+        let result = data.execute(&lua, "run".to_owned(), ()).unwrap().to_string();
+        let expected = BluefoxData::try_from("
+        list: [1, null, []]
+        zero: { 0: [] }
+        negative: { -1: [] }
+        sparse: { 1000000: [] }").unwrap();
+        for key in ["list", "zero", "negative", "sparse"] {
+            assert_eq!(data.data[key], expected.data[key]);
+        }
+        assert_eq!(result, expected.data["list"].to_string());
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use super::{BluefoxData, BluefoxDataType, BluefoxError, PathSegment, parse_path, path_to_string};
+
+// resolves `${a.b[0]}` references in strings against the whole document, a string that is nothing
+// but a reference takes the referenced value with its type, `$${` is a literal `${`
+struct Resolver<'d, 'a> {
+    data: &'d BluefoxData<'a>,
+    resolved: HashMap<String, BluefoxDataType<'a>>,
+    // the paths currently being resolved, for cycle detection
+    stack: Vec<String>
+}
+
+fn descend<'v, 'a>(mut value: &'v BluefoxDataType<'a>, path: &[PathSegment]) -> Option<&'v BluefoxDataType<'a>> {
+    for segment in path {
+        value = match (value, segment) {
+            (BluefoxDataType::DATA(x), PathSegment::Key(k)) => x.data.get(k)?,
+            (BluefoxDataType::ARRAY(x), PathSegment::Index(i)) => x.get(*i)?,
+            _ => return None
+        };
+    }
+    Some(value)
+}
+
+fn is_reference(value: &BluefoxDataType) -> bool {
+    match value {
+        BluefoxDataType::STRING(x) => x.contains("${"),
+        _ => false
+    }
+}
+
+impl <'d, 'a> Resolver<'d, 'a> {
+    fn resolve_at(&mut self, path: &[PathSegment]) -> Result<BluefoxDataType<'a>, BluefoxError> {
+        let key = path_to_string(path);
+        if let Some(x) = self.resolved.get(&key) {
+            return Ok(x.clone());
+        }
+        if let Some(i) = self.stack.iter().position(|x| x == &key) {
+            let mut chain = self.stack[i..].to_vec();
+            chain.push(key);
+            return Err(BluefoxError::ReferenceCycle { chain });
+        }
+
+        let raw = match self.data.find(path) {
+            Some(x) => x,
+            None => return Err(BluefoxError::missing_key(&key))
+        };
+        self.stack.push(key.clone());
+        let value = match raw {
+            BluefoxDataType::STRING(x) => self.interpolate(x, &key),
+            BluefoxDataType::ARRAY(x) => {
+                let mut output = vec![];
+                let mut path = path.to_vec();
+                for i in 0..x.len() {
+                    path.push(PathSegment::Index(i));
+                    output.push(self.resolve_at(&path));
+                    path.pop();
+                }
+                output.into_iter().collect::<Result<_, _>>().map(BluefoxDataType::ARRAY)
+            },
+            BluefoxDataType::DATA(x) => {
+                let mut output = BluefoxData::new();
+                let mut path = path.to_vec();
+                let mut result = Ok(());
+                for k in x.data.keys() {
+                    path.push(PathSegment::Key(k.clone()));
+                    match self.resolve_at(&path) {
+                        Ok(v) => {
+                            output.data.insert(k.clone(), v);
+                        },
+                        Err(e) => {
+                            result = Err(e);
+                            break;
+                        }
+                    }
+                    path.pop();
+                }
+                result.map(|_| BluefoxDataType::DATA(output))
+            },
+            other => Ok(other.clone())
+        };
+        self.stack.pop();
+        let value = value?;
+        self.resolved.insert(key, value.clone());
+        Ok(value)
+    }
+
+    // the value a reference points at, a reference may lead through values that are references themselves
+    fn lookup(&mut self, reference: &str, from: &str) -> Result<BluefoxDataType<'a>, BluefoxError> {
+        let path = parse_path(reference)?;
+        let unresolved = || BluefoxError::UnresolvedReference { path: from.to_owned(), reference: reference.to_owned() };
+        for n in 1..path.len() {
+            match self.data.find(&path[..n]) {
+                None => return Err(unresolved()),
+                Some(x) if is_reference(x) => {
+                    let value = self.resolve_at(&path[..n])?;
+                    return descend(&value, &path[n..]).cloned().ok_or_else(unresolved);
+                },
+                _ => {}
+            }
+        }
+        match self.resolve_at(&path) {
+            Err(BluefoxError::MissingKey { .. }) => Err(unresolved()),
+            other => other
+        }
+    }
+
+    fn interpolate(&mut self, text: &str, path: &str) -> Result<BluefoxDataType<'a>, BluefoxError> {
+        let trimmed = text.trim();
+        if trimmed.starts_with("${") && trimmed.find('}') == Some(trimmed.len() - 1) {
+            return self.lookup(trimmed[2..trimmed.len() - 1].trim(), path);
+        }
+
+        let mut output = "".to_owned();
+        let mut rest = text;
+        while let Some(i) = rest.find('$') {
+            output += &rest[..i];
+            rest = &rest[i..];
+            if rest.starts_with("$${") {
+                output += "${";
+                rest = &rest[3..];
+            }
+            else if rest.starts_with("${") {
+                let end = match rest.find('}') {
+                    Some(x) => x,
+                    None => return Err(BluefoxError::InvalidPath { path: rest[2..].to_owned(), reason: "expected '}' to close reference".to_owned() })
+                };
+                let reference = rest[2..end].trim();
+                output += &match self.lookup(reference, path)? {
+                    BluefoxDataType::STRING(x) => x,
+                    other @ (BluefoxDataType::ARRAY(_) | BluefoxDataType::DATA(_) | BluefoxDataType::FUNCTION(..) | BluefoxDataType::BYTES(_) | BluefoxDataType::NULL) => {
+                        return Err(BluefoxError::type_mismatch(path, "a value that can be put in a string", other.type_name()))
+                    },
+                    other => other.to_string()
+                };
+                rest = &rest[end + 1..];
+            }
+            else {
+                output.push('$');
+                rest = &rest[1..];
+            }
+        }
+        output += rest;
+        Ok(BluefoxDataType::STRING(output))
+    }
+}
+
+impl <'a> BluefoxData<'a> {
+    // a copy with every reference replaced by what it points at, self stays the unresolved view
+    pub fn resolved(&self) -> Result<BluefoxData<'a>, BluefoxError> {
+        let mut resolver = Resolver {
+            data: self,
+            resolved: HashMap::new(),
+            stack: vec![]
+        };
+        let mut output = BluefoxData::new();
+        for k in self.data.keys() {
+            output.data.insert(k.clone(), resolver.resolve_at(&[PathSegment::Key(k.clone())])?);
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn references() {
+        let data = BluefoxData::try_from("
+        server: { host: example.com, port: 8080 }
+        url: 'http://${server.host}:${server.port}/api'
+        port: '${server.port}'
+        backup: '${server}'
+        backup_host: '${backup.host}'
+        mirrors: ['${url}', 'http://${ mirrors[2] }', '${backup_host}']
+        price: '$$5 or $${not.a.reference}'").unwrap();
+        let resolved = data.resolved().unwrap();
+        assert_eq!(resolved.to_string(), "server: { host: \"example.com\", port: 8080 }
+url: \"http://example.com:8080/api\"
+port: 8080
+backup: { host: \"example.com\", port: 8080 }
+backup_host: \"example.com\"
+mirrors: [
+    \"http://example.com:8080/api\"
+    \"http://example.com\"
+    \"example.com\"
+]
+price: \"$$5 or ${not.a.reference}\"
+");
+        // the unresolved view is left as it was
+        assert_eq!(data.get_as::<String>("port").unwrap(), "${server.port}");
+
+        match BluefoxData::try_from("a: '${b}'\nb: { c: 'x ${a}' }").unwrap().resolved() {
+            Err(BluefoxError::ReferenceCycle { chain }) => assert_eq!(chain, vec!["a", "b", "b.c", "a"]),
+            other => panic!("expected reference cycle, got {:?}", other)
+        }
+        match BluefoxData::try_from("a: { b: 'port ${c.d}' }\nc: {}").unwrap().resolved() {
+            Err(BluefoxError::UnresolvedReference { path, reference }) => assert_eq!((path.as_str(), reference.as_str()), ("a.b", "c.d")),
+            other => panic!("expected unresolved reference, got {:?}", other)
+        }
+        assert!(BluefoxData::try_from("a: [1]\nb: 'x ${a}'").unwrap().resolved().is_err());
+    }
+}
@@ -0,0 +1,255 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, AtomicU64, Ordering}
+};
+use mlua::prelude::*;
+use mlua::HookTriggers;
+
+use super::{BluefoxData, BluefoxDataType, BluefoxError, proxy};
+
+// the globals sandboxed code gets, everything that reaches the filesystem, other processes or
+// loads code is left out: io, os.execute and friends, require, load, dofile, debug, package,
+// and getmetatable, which would hand out the string metatable shared with the rest of the state
+const GLOBALS: [&str; 16] = [
+    "assert", "error", "ipairs", "next", "pairs", "pcall", "select", "tonumber", "tostring", "type", "xpcall",
+    "rawequal", "rawget", "rawlen", "rawset", "setmetatable"
+];
+const LIBRARIES: [&str; 5] = ["string", "table", "math", "utf8", "coroutine"];
+const OS: [&str; 4] = ["clock", "date", "difftime", "time"];
+
+// how often the instruction hook runs, the limit is checked with this granularity
+const HOOK_INTERVAL: u64 = 1000;
+
+// the error object lua hands pcall when an allocation fails
+const MEMORY_ERROR: &str = "not enough memory";
+
+// pcall, xpcall and coroutine.resume that pass every error on as usual except the ones from going
+// over a limit, which are raised again so the sandboxed code can't catch them and carry on, and a
+// setmetatable that refuses __gc and __close, finalizers would run after the limits are lifted
+const WRAPPERS: &str = "
+local pcall, xpcall, resume, setmetatable, limited = ...
+local error, rawget, type = error, rawget, type
+local function check(ok, ...)
+    if not ok and limited((...)) then
+        error((...), 0)
+    end
+    return ok, ...
+end
+return function(...) return check(pcall(...)) end,
+    function(...) return check(xpcall(...)) end,
+    function(...) return check(resume(...)) end,
+    function(t, mt)
+        if type(mt) == 'table' and (rawget(mt, '__gc') ~= nil or rawget(mt, '__close') ~= nil) then
+            error('metatables with __gc or __close are not allowed in the sandbox', 2)
+        end
+        return setmetatable(t, mt)
+    end
+";
+
+#[derive(Clone, Debug, Default)]
+pub struct Sandbox {
+    instruction_limit: Option<u64>,
+    memory_limit: Option<usize>
+}
+impl Sandbox {
+    pub fn new() -> Self {
+        Self {
+            instruction_limit: None,
+            memory_limit: None
+        }
+    }
+
+    pub fn instruction_limit(mut self, limit: u64) -> Self {
+        self.instruction_limit = Some(limit);
+        self
+    }
+
+    // bytes the call may allocate on top of what the lua state already uses
+    pub fn memory_limit(mut self, limit: usize) -> Self {
+        self.memory_limit = Some(limit);
+        self
+    }
+
+    // a fresh environment per call, libraries are copied so changes to them don't outlive the call,
+    // with an instruction limit there are no coroutines since the hook only sees the main thread
+    fn environment<'lua>(&self, lua: &'lua Lua, limited: LuaFunction<'lua>) -> LuaResult<LuaTable<'lua>> {
+        let globals = lua.globals();
+        let env = lua.create_table()?;
+        for name in GLOBALS {
+            env.set(name, globals.get::<_, LuaValue>(name)?)?;
+        }
+        for name in LIBRARIES {
+            if name == "coroutine" && self.instruction_limit.is_some() {
+                continue;
+            }
+            let library = lua.create_table()?;
+            for pair in globals.get::<_, LuaTable>(name)?.pairs::<LuaValue, LuaValue>() {
+                let (k, v) = pair?;
+                library.set(k, v)?;
+            }
+            env.set(name, library)?;
+        }
+        let os = lua.create_table()?;
+        let global_os: LuaTable = globals.get("os")?;
+        for name in OS {
+            os.set(name, global_os.get::<_, LuaValue>(name)?)?;
+        }
+        env.set("os", os)?;
+        env.set("_G", env.clone())?;
+
+        if self.instruction_limit.is_some() || self.memory_limit.is_some() {
+            let coroutine: LuaTable = globals.get("coroutine")?;
+            let (pcall, xpcall, resume, setmetatable): (LuaFunction, LuaFunction, LuaFunction, LuaFunction) = lua.load(WRAPPERS)
+                .call((globals.get::<_, LuaFunction>("pcall")?, globals.get::<_, LuaFunction>("xpcall")?, coroutine.get::<_, LuaFunction>("resume")?,
+                    globals.get::<_, LuaFunction>("setmetatable")?, limited))?;
+            env.set("pcall", pcall)?;
+            env.set("xpcall", xpcall)?;
+            env.set("setmetatable", setmetatable)?;
+            if let Ok(LuaValue::Table(x)) = env.get("coroutine") {
+                x.set("resume", resume)?;
+            }
+        }
+        Ok(env)
+    }
+}
+
+fn is_memory_error(e: &LuaError) -> bool {
+    match e {
+        LuaError::MemoryError(_) => true,
+        LuaError::CallbackError { cause, .. } => is_memory_error(cause),
+        _ => false
+    }
+}
+
+impl <'a> BluefoxData<'a> {
+    // like execute, but the function only sees a restricted set of globals and the document as
+    // `notation`, and is stopped with BluefoxError::SandboxLimit when it goes over a limit, an
+    // instruction limit takes over the hook of the lua state, any hook set before is removed
+    pub fn execute_sandboxed<A>(&mut self, lua: &'a Lua, func: String, args: A, sandbox: &Sandbox) -> Result<BluefoxDataType<'a>, BluefoxError> where A: IntoLuaMulti<'a> {
+        let exceeded = Arc::new(AtomicBool::new(false));
+        let out_of_memory = Arc::new(AtomicBool::new(false));
+        if let Some(limit) = sandbox.instruction_limit {
+            let count = Arc::new(AtomicU64::new(0));
+            let exceeded = exceeded.clone();
+            let interval = HOOK_INTERVAL.min(limit.max(1));
+            lua.set_hook(HookTriggers::new().every_nth_instruction(interval as u32), move |_, _| {
+                if count.fetch_add(interval, Ordering::Relaxed) + interval > limit {
+                    exceeded.store(true, Ordering::Relaxed);
+                    return Err(LuaError::runtime("instruction limit exceeded"));
+                }
+                Ok(())
+            });
+        }
+        let previous_memory_limit = match sandbox.memory_limit {
+            Some(limit) => Some(lua.set_memory_limit(lua.used_memory() + limit).map_err(|e| BluefoxError::Lua { chunk: func.clone(), source: e })?),
+            None => None
+        };
+
+        let result = self.execute_sandboxed_inner(lua, &func, args, sandbox, exceeded.clone(), out_of_memory.clone());
+
+        if sandbox.instruction_limit.is_some() {
+            lua.remove_hook();
+        }
+        if let Some(x) = previous_memory_limit {
+            let _ = lua.set_memory_limit(x);
+        }
+
+        // a limit that was hit counts even if the code got past it and returned normally
+        let out_of_memory = out_of_memory.load(Ordering::Relaxed) || matches!(&result, Err(e) if is_memory_error(e));
+        if exceeded.load(Ordering::Relaxed) {
+            return Err(BluefoxError::SandboxLimit { chunk: func, limit: format!("instruction limit of {}", sandbox.instruction_limit.unwrap()) });
+        }
+        if let (Some(limit), true) = (sandbox.memory_limit, out_of_memory) {
+            return Err(BluefoxError::SandboxLimit { chunk: func, limit: format!("memory limit of {} bytes", limit) });
+        }
+        result.map_err(|e| BluefoxError::Lua { chunk: func, source: e })
+    }
+
+    fn execute_sandboxed_inner<A>(&mut self, lua: &'a Lua, func: &str, args: A, sandbox: &Sandbox, exceeded: Arc<AtomicBool>, out_of_memory: Arc<AtomicBool>) -> LuaResult<BluefoxDataType<'a>> where A: IntoLuaMulti<'a> {
+        let memory_limit = sandbox.memory_limit.is_some();
+        let limited = lua.create_function(move |_, error: LuaValue| {
+            if memory_limit && matches!(&error, LuaValue::String(x) if x == MEMORY_ERROR) {
+                out_of_memory.store(true, Ordering::Relaxed);
+            }
+            Ok(exceeded.load(Ordering::Relaxed) || out_of_memory.load(Ordering::Relaxed))
+        })?;
+        proxy::call(self, lua, sandbox.environment(lua, limited)?, func, args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sandbox() {
+        let lua = Lua::new();
+        let mut data = BluefoxData::try_from("
+        port: 8080
+        bump: `notation.port = notation.port + 1; return string.format('%d', notation.port)`
+        read: `return io.open('/etc/passwd')`
+        run: `return os.execute('true')`
+        global: `leaked = true; string.leaked = true`
+        spin: `while true do end`
+        grow: `local t = {} for i = 1, 1e8 do t[i] = i end`
+        catch_spin: `while true do pcall(function() while true do end end) end`
+        catch_grow: `local ok = xpcall(function() local t = {} for i = 1, 1e8 do t[i] = i end end, tostring) return ok`
+        catch_error: `local ok, e = pcall(error, { code = 7 }) return e.code`
+        has_coroutine: `return coroutine ~= nil`
+        finalizer: `setmetatable({}, { __gc = function() while true do end end })`
+        late_finalizer: `local mt = { __gc = false } setmetatable({}, mt) mt.__gc = function() while true do end end`
+        plain_metatable: `return setmetatable({}, { __index = function() return 3 end }).x`").unwrap();
+        let sandbox = Sandbox::new().instruction_limit(100_000).memory_limit(1_000_000);
+
+        assert_eq!(data.execute_sandboxed(&lua, "bump".to_owned(), (), &sandbox).unwrap(), BluefoxDataType::STRING("8081".to_owned()));
+        assert_eq!(data.data["port"], BluefoxDataType::INT(8081));
+
+        for chunk in ["read", "run"] {
+            match data.execute_sandboxed(&lua, chunk.to_owned(), (), &sandbox) {
+                Err(BluefoxError::Lua { chunk: x, .. }) => assert_eq!(x, chunk),
+                other => panic!("expected lua error, got {:?}", other)
+            }
+        }
+        data.execute_sandboxed(&lua, "global".to_owned(), (), &sandbox).unwrap();
+        assert_eq!(lua.globals().get::<_, LuaValue>("leaked").unwrap(), LuaValue::Nil);
+        assert_eq!(lua.load("return string.leaked").eval::<LuaValue>().unwrap(), LuaValue::Nil);
+
+        match data.execute_sandboxed(&lua, "spin".to_owned(), (), &sandbox) {
+            Err(BluefoxError::SandboxLimit { chunk, limit }) => assert_eq!((chunk.as_str(), limit.as_str()), ("spin", "instruction limit of 100000")),
+            other => panic!("expected sandbox limit, got {:?}", other)
+        }
+        match data.execute_sandboxed(&lua, "grow".to_owned(), (), &Sandbox::new().memory_limit(1_000_000)) {
+            Err(BluefoxError::SandboxLimit { limit, .. }) => assert_eq!(limit, "memory limit of 1000000 bytes"),
+            other => panic!("expected sandbox limit, got {:?}", other)
+        }
+
+        // limit errors can't be caught, other errors still can
+        match data.execute_sandboxed(&lua, "catch_spin".to_owned(), (), &sandbox) {
+            Err(BluefoxError::SandboxLimit { limit, .. }) => assert_eq!(limit, "instruction limit of 100000"),
+            other => panic!("expected sandbox limit, got {:?}", other)
+        }
+        match data.execute_sandboxed(&lua, "catch_grow".to_owned(), (), &Sandbox::new().memory_limit(1_000_000)) {
+            Err(BluefoxError::SandboxLimit { limit, .. }) => assert_eq!(limit, "memory limit of 1000000 bytes"),
+            other => panic!("expected sandbox limit, got {:?}", other)
+        }
+        assert_eq!(data.execute_sandboxed(&lua, "catch_error".to_owned(), (), &sandbox).unwrap(), BluefoxDataType::INT(7));
+
+        // the instruction hook only sees the main thread, so coroutines go with an instruction limit
+        assert_eq!(data.execute_sandboxed(&lua, "has_coroutine".to_owned(), (), &sandbox).unwrap(), BluefoxDataType::BOOL(false));
+        assert_eq!(data.execute_sandboxed(&lua, "has_coroutine".to_owned(), (), &Sandbox::new()).unwrap(), BluefoxDataType::BOOL(true));
+
+        // finalizers would run at the host's next collection, outside the limits
+        for chunk in ["finalizer", "late_finalizer"] {
+            match data.execute_sandboxed(&lua, chunk.to_owned(), (), &sandbox) {
+                Err(BluefoxError::Lua { chunk: x, .. }) => assert_eq!(x, chunk),
+                other => panic!("expected lua error, got {:?}", other)
+            }
+        }
+        lua.gc_collect().unwrap();
+        lua.gc_collect().unwrap();
+        assert_eq!(data.execute_sandboxed(&lua, "plain_metatable".to_owned(), (), &sandbox).unwrap(), BluefoxDataType::INT(3));
+
+        // limits are lifted again afterwards
+        lua.load("local n = 0 for i = 1, 1e6 do n = n + i end local t = {} for i = 1, 1e5 do t[i] = i end").exec().unwrap();
+    }
+}
@@ -0,0 +1,460 @@
+use std::fmt;
+use indexmap::IndexMap;
+use mlua::prelude::*;
+use regex::Regex;
+
+use super::{BluefoxData, BluefoxDataType, BluefoxDeserialize, BluefoxError, FromBluefoxValue, PathSegment, Sandbox, path_to_string};
+
+// a schema is written in notation, every key is optional:
+// type: int                 a type name, or an array of them, "number" is int or float and "any" is anything
+// required: true            the key has to be there, unless it has a default
+// default: 8080             the value apply_defaults fills in for a missing key
+// min: 1, max: 65535        bounds for ints and floats
+// min_length, max_length    bounds for the length of strings, bytes, arrays and data
+// pattern: "^[a-z]+$"       a regex strings have to match
+// enum: [fast, slow]        the only values allowed
+// fields: { port: {...} }   schemas for the keys of data, a field can also be just a type name like `port: int`
+// additional: false         keys not listed in fields are not allowed
+// items: {...}              the schema every item of an array has to match
+// validate: `...`           a lua function given the value and its path, returning false or a message when it's invalid, run in a sandbox
+#[derive(Clone, Debug)]
+pub struct Schema {
+    types: Vec<String>,
+    required: bool,
+    default: Option<BluefoxDataType<'static>>,
+    min: Option<f64>,
+    max: Option<f64>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    pattern: Option<Regex>,
+    one_of: Option<Vec<BluefoxDataType<'static>>>,
+    fields: Option<IndexMap<String, Schema>>,
+    additional: bool,
+    items: Option<Box<Schema>>,
+    validate: Option<String>
+}
+
+// how long a validator may run and how much it may allocate before it counts as failed
+const VALIDATOR_INSTRUCTIONS: u64 = 10_000_000;
+const VALIDATOR_MEMORY: usize = 16 * 1024 * 1024;
+
+const TYPES: [&str; 15] = [
+    "null", "bool", "int", "float", "string", "datetime", "date", "time", "duration", "bytes", "function", "array", "data", "number", "any"
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation {
+    pub path: String,
+    pub message: String
+}
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        }
+        else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+// values kept by the schema, or handed to lua, can't hold on to a function compiled by some other lua state
+fn detach<'x>(value: &BluefoxDataType) -> BluefoxDataType<'x> {
+    match value {
+        BluefoxDataType::NULL => BluefoxDataType::NULL,
+        BluefoxDataType::BOOL(x) => BluefoxDataType::BOOL(*x),
+        BluefoxDataType::INT(x) => BluefoxDataType::INT(*x),
+        BluefoxDataType::FLOAT(x) => BluefoxDataType::FLOAT(*x),
+        BluefoxDataType::STRING(x) => BluefoxDataType::STRING(x.clone()),
+        BluefoxDataType::DATETIME(x) => BluefoxDataType::DATETIME(*x),
+        BluefoxDataType::DATE(x) => BluefoxDataType::DATE(*x),
+        BluefoxDataType::TIME(x) => BluefoxDataType::TIME(*x),
+        BluefoxDataType::DURATION(x) => BluefoxDataType::DURATION(*x),
+        BluefoxDataType::BYTES(x) => BluefoxDataType::BYTES(x.clone()),
+        BluefoxDataType::FUNCTION(x, _) => BluefoxDataType::FUNCTION(x.clone(), None),
+        BluefoxDataType::ARRAY(x) => BluefoxDataType::ARRAY(x.iter().map(detach).collect()),
+        BluefoxDataType::DATA(x) => {
+            let mut output = BluefoxData::new();
+            for (k, v) in &x.data {
+                output.data.insert(k.clone(), detach(v));
+            }
+            BluefoxDataType::DATA(output)
+        }
+    }
+}
+
+fn length(value: &BluefoxDataType) -> Option<usize> {
+    match value {
+        BluefoxDataType::STRING(x) => Some(x.chars().count()),
+        BluefoxDataType::BYTES(x) => Some(x.len()),
+        BluefoxDataType::ARRAY(x) => Some(x.len()),
+        BluefoxDataType::DATA(x) => Some(x.data.len()),
+        _ => None
+    }
+}
+
+fn type_matches(name: &str, value: &BluefoxDataType) -> bool {
+    match (name, value) {
+        ("any", _) => true,
+        ("number", BluefoxDataType::INT(_) | BluefoxDataType::FLOAT(_)) => true,
+        // same as reading an f64, a float may be written without a fraction
+        ("float", BluefoxDataType::INT(_)) => true,
+        (name, value) => name == value.type_name()
+    }
+}
+
+struct Context<'l> {
+    lua: &'l Lua,
+    path: Vec<PathSegment>,
+    violations: Vec<Violation>
+}
+impl Context<'_> {
+    fn violation(&mut self, message: String) {
+        self.violations.push(Violation {
+            path: path_to_string(&self.path),
+            message
+        });
+    }
+}
+
+impl Schema {
+    pub fn parse(source: &str) -> Result<Self, BluefoxError> {
+        Self::from_data(BluefoxData::try_from(source)?)
+    }
+
+    pub fn from_file(file: String) -> Result<Self, BluefoxError> {
+        Self::from_data(BluefoxData::from_file(file)?)
+    }
+
+    // checks value against the schema and returns every violation found, not just the first
+    pub fn validate(&self, data: &BluefoxData) -> Result<(), Vec<Violation>> {
+        self.validate_with(&Lua::new(), data)
+    }
+
+    // the same as validate, but validator functions run sandboxed in the given lua state
+    pub fn validate_with(&self, lua: &Lua, data: &BluefoxData) -> Result<(), Vec<Violation>> {
+        let mut context = Context {
+            lua,
+            path: vec![],
+            violations: vec![]
+        };
+        self.check(&BluefoxDataType::DATA(data.clone()), &mut context);
+        if context.violations.is_empty() {
+            Ok(())
+        }
+        else {
+            Err(context.violations)
+        }
+    }
+
+    // fills in the defaults of missing keys, including in nested data and arrays of data
+    pub fn apply_defaults<'a>(&self, data: &mut BluefoxData<'a>) {
+        if let Some(fields) = &self.fields {
+            for (k, schema) in fields {
+                if let (None, Some(x)) = (data.data.get(k), &schema.default) {
+                    data.data.insert(k.clone(), detach(x));
+                }
+                if let Some(x) = data.data.get_mut(k) {
+                    schema.apply_defaults_to(x);
+                }
+            }
+        }
+    }
+
+    fn apply_defaults_to(&self, value: &mut BluefoxDataType) {
+        match value {
+            BluefoxDataType::DATA(x) => self.apply_defaults(x),
+            BluefoxDataType::ARRAY(x) => {
+                if let Some(items) = &self.items {
+                    for i in x {
+                        items.apply_defaults_to(i);
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn check(&self, value: &BluefoxDataType, context: &mut Context) {
+        if !self.types.is_empty() && !self.types.iter().any(|t| type_matches(t, value)) {
+            context.violation(format!("expected {}, found {}", self.types.join(" or "), value.type_name()));
+            return;
+        }
+
+        if let Some(x) = value.as_f64() {
+            if let Some(min) = self.min.filter(|min| x < *min) {
+                context.violation(format!("expected at least {}, found {}", min, value.to_string()));
+            }
+            if let Some(max) = self.max.filter(|max| x > *max) {
+                context.violation(format!("expected at most {}, found {}", max, value.to_string()));
+            }
+        }
+        if let Some(x) = length(value) {
+            if let Some(min) = self.min_length.filter(|min| x < *min) {
+                context.violation(format!("expected a length of at least {}, found {}", min, x));
+            }
+            if let Some(max) = self.max_length.filter(|max| x > *max) {
+                context.violation(format!("expected a length of at most {}, found {}", max, x));
+            }
+        }
+        if let (Some(pattern), BluefoxDataType::STRING(x)) = (&self.pattern, value) {
+            if !pattern.is_match(x) {
+                context.violation(format!("expected to match /{}/, found {}", pattern.as_str(), value.to_string()));
+            }
+        }
+        if let Some(one_of) = &self.one_of {
+            if !one_of.contains(value) {
+                let names: Vec<String> = one_of.iter().map(|x| x.to_string()).collect();
+                context.violation(format!("expected one of {}, found {}", names.join(", "), value.to_string()));
+            }
+        }
+
+        match value {
+            BluefoxDataType::DATA(x) => {
+                if let Some(fields) = &self.fields {
+                    for (k, schema) in fields {
+                        context.path.push(PathSegment::Key(k.clone()));
+                        match x.data.get(k) {
+                            Some(v) => schema.check(v, context),
+                            None if schema.required && schema.default.is_none() => context.violation("missing required key".to_owned()),
+                            None => {}
+                        }
+                        context.path.pop();
+                    }
+                    if !self.additional {
+                        for k in x.data.keys().filter(|k| !fields.contains_key(*k)) {
+                            context.path.push(PathSegment::Key(k.clone()));
+                            context.violation("unknown key".to_owned());
+                            context.path.pop();
+                        }
+                    }
+                }
+            },
+            BluefoxDataType::ARRAY(x) => {
+                if let Some(items) = &self.items {
+                    for (i, v) in x.iter().enumerate() {
+                        context.path.push(PathSegment::Index(i));
+                        items.check(v, context);
+                        context.path.pop();
+                    }
+                }
+            },
+            _ => {}
+        }
+
+        if let Some(source) = &self.validate {
+            if let Some(message) = Self::run_validator(source, value, context) {
+                context.violation(message);
+            }
+        }
+    }
+
+    // a validator passes by returning nothing or true, false or a string is a violation,
+    // it comes from the schema file so it runs sandboxed like any other untrusted lua
+    fn run_validator<'l>(source: &str, value: &BluefoxDataType, context: &Context<'l>) -> Option<String> {
+        let mut validator = BluefoxData::new();
+        validator.data.insert("validate".to_owned(), BluefoxDataType::FUNCTION(source.to_owned(), None));
+        let args = (detach(value), path_to_string(&context.path));
+        let sandbox = Sandbox::new().instruction_limit(VALIDATOR_INSTRUCTIONS).memory_limit(VALIDATOR_MEMORY);
+        match validator.execute_sandboxed(context.lua, "validate".to_owned(), args, &sandbox) {
+            Ok(BluefoxDataType::BOOL(false)) => Some("failed validation".to_owned()),
+            Ok(BluefoxDataType::STRING(x)) => Some(x),
+            Ok(_) => None,
+            Err(e) => Some(format!("validator failed: {}", e))
+        }
+    }
+}
+impl <'a> FromBluefoxValue<'a> for Schema {
+    fn from_value(value: BluefoxDataType<'a>) -> Result<Self, BluefoxError> {
+        let mut output = Schema {
+            types: vec![],
+            required: false,
+            default: None,
+            min: None,
+            max: None,
+            min_length: None,
+            max_length: None,
+            pattern: None,
+            one_of: None,
+            fields: None,
+            additional: true,
+            items: None,
+            validate: None
+        };
+        let data = match value {
+            // a bare type name is short for { type: name }
+            BluefoxDataType::STRING(_) | BluefoxDataType::ARRAY(_) => {
+                output.types = type_names(value)?;
+                return Ok(output);
+            },
+            BluefoxDataType::DATA(x) => x,
+            other => return Err(BluefoxError::type_mismatch("", "schema", other.type_name()))
+        };
+
+        for (k, v) in data.data {
+            let result = match &k[..] {
+                "type" => type_names(v).map(|x| output.types = x),
+                "required" => bool::from_value(v).map(|x| output.required = x),
+                "default" => {
+                    output.default = Some(detach(&v));
+                    Ok(())
+                },
+                "min" => f64::from_value(v).map(|x| output.min = Some(x)),
+                "max" => f64::from_value(v).map(|x| output.max = Some(x)),
+                "min_length" => usize::from_value(v).map(|x| output.min_length = Some(x)),
+                "max_length" => usize::from_value(v).map(|x| output.max_length = Some(x)),
+                "pattern" => String::from_value(v).and_then(|x| {
+                    Regex::new(&x).map(|x| output.pattern = Some(x)).map_err(|e| BluefoxError::type_mismatch("", "regex", &e.to_string()))
+                }),
+                "enum" => Vec::<BluefoxDataType>::from_value(v).map(|x| output.one_of = Some(x.iter().map(detach).collect())),
+                "fields" => IndexMap::<String, Schema>::from_value(v).map(|x| output.fields = Some(x)),
+                "additional" => bool::from_value(v).map(|x| output.additional = x),
+                "items" => Schema::from_value(v).map(|x| output.items = Some(Box::new(x))),
+                "validate" => match v {
+                    BluefoxDataType::FUNCTION(x, _) => {
+                        output.validate = Some(x);
+                        Ok(())
+                    },
+                    other => Err(BluefoxError::type_mismatch("", "function", other.type_name()))
+                },
+                _ => Err(BluefoxError::type_mismatch("", "schema key", &format!("\"{}\"", k)))
+            };
+            result.map_err(|e| e.context(&k))?;
+        }
+        if output.types.is_empty() && output.fields.is_some() {
+            output.types = vec!["data".to_owned()];
+        }
+        Ok(output)
+    }
+}
+impl <'a> BluefoxDeserialize<'a> for Schema {
+    fn from_data(data: BluefoxData<'a>) -> Result<Self, BluefoxError> {
+        Schema::from_value(BluefoxDataType::DATA(data))
+    }
+}
+
+fn type_names(value: BluefoxDataType) -> Result<Vec<String>, BluefoxError> {
+    let names = match value {
+        BluefoxDataType::STRING(x) => vec![x],
+        other => Vec::<String>::from_value(other)?
+    };
+    for name in &names {
+        if !TYPES.contains(&&name[..]) {
+            return Err(BluefoxError::type_mismatch("", "type name", &format!("\"{}\"", name)));
+        }
+    }
+    Ok(names)
+}
+
+impl <'a> FromBluefoxValue<'a> for IndexMap<String, Schema> {
+    fn from_value(value: BluefoxDataType<'a>) -> Result<Self, BluefoxError> {
+        let mut output = IndexMap::new();
+        for (k, v) in BluefoxData::from_value(value)?.data {
+            let schema = Schema::from_value(v).map_err(|e| e.context(&k))?;
+            output.insert(k, schema);
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = "
+    additional: false
+    fields: {
+        host: { type: string, required: true, pattern: '^[a-z.]+$' }
+        port: { type: int, min: 1, max: 65535, default: 8080 }
+        mode: { enum: [fast, slow], default: fast }
+        ratio: number
+        tags: { type: array, max_length: 2, items: string }
+        servers: {
+            type: array
+            items: {
+                fields: {
+                    name: { type: string, required: true }
+                    weight: { type: int, default: 1 }
+                }
+            }
+        }
+        level: {
+            type: int
+            validate: `
+                local value, path = ...
+                if value % 2 ~= 0 then
+                    return path .. ' has to be even'
+                end
+            `
+        }
+    }";
+
+    #[test]
+    fn validate() {
+        let schema = Schema::parse(SCHEMA).unwrap();
+        let good = BluefoxData::try_from("host: example.com\nratio: 1\nservers: [{ name: a }]\nlevel: 4").unwrap();
+        assert_eq!(schema.validate(&good), Ok(()));
+
+        let bad = BluefoxData::try_from("
+        port: 70000
+        mode: medium
+        ratio: yes
+        tags: [a, 2, c]
+        servers: [{ weight: 2 }]
+        level: 3
+        extra: true").unwrap();
+        let violations: Vec<String> = schema.validate(&bad).unwrap_err().iter().map(|x| x.to_string()).collect();
+        assert_eq!(violations, vec![
+            "host: missing required key",
+            "port: expected at most 65535, found 70000",
+            "mode: expected one of \"fast\", \"slow\", found \"medium\"",
+            "ratio: expected number, found string",
+            "tags: expected a length of at most 2, found 3",
+            "tags[1]: expected string, found int",
+            "servers[0].name: missing required key",
+            "level: level has to be even",
+            "extra: unknown key"
+        ]);
+
+        let bad = BluefoxData::try_from("host: Example.com").unwrap();
+        assert_eq!(schema.validate(&bad).unwrap_err()[0].to_string(), "host: expected to match /^[a-z.]+$/, found \"Example.com\"");
+        // validators come from the schema file and only get the sandbox
+        let schema = Schema::parse("fields: {
+            a: { validate: `return io.open('/etc/passwd') == nil` }
+            b: { validate: `os.execute('true')` }
+            c: { validate: `while true do end` }
+            d: { validate: `return #string.rep('x', 2^30) > 0` }
+        }").unwrap();
+        let data = BluefoxData::try_from("a: 1\nb: 2\nc: 3\nd: 4").unwrap();
+        let violations = schema.validate(&data).unwrap_err();
+        assert_eq!(violations.iter().map(|x| x.path.as_str()).collect::<Vec<_>>(), vec!["a", "b", "c", "d"]);
+        assert!(violations.iter().all(|x| x.message.starts_with("validator failed: ")));
+        assert!(violations[2].message.contains("instruction limit"));
+        assert!(violations[3].message.contains("memory limit"), "{}", violations[3].message);
+    }
+
+    #[test]
+    fn defaults() {
+        let schema = Schema::parse(SCHEMA).unwrap();
+        let mut data = BluefoxData::try_from("host: example.com\nservers: [{ name: a }, { name: b, weight: 3 }]").unwrap();
+        schema.apply_defaults(&mut data);
+        assert_eq!(data.to_string(), "host: \"example.com\"
+servers: [{ name: \"a\", weight: 1 }, { name: \"b\", weight: 3 }]
+port: 8080
+mode: \"fast\"
+");
+    }
+
+    #[test]
+    fn bad_schemas() {
+        match Schema::parse("fields: { port: { type: integer } }") {
+            Err(BluefoxError::TypeMismatch { path, .. }) => assert_eq!(path, "fields.port.type"),
+            other => panic!("expected type mismatch, got {:?}", other)
+        }
+        match Schema::parse("fields: { name: { pattern: '(' } }") {
+            Err(BluefoxError::TypeMismatch { path, expected, .. }) => assert_eq!((path.as_str(), expected.as_str()), ("fields.name.pattern", "regex")),
+            other => panic!("expected type mismatch, got {:?}", other)
+        }
+        assert!(Schema::parse("fields: { port: { minimum: 1 } }").is_err());
+    }
+}
@@ -0,0 +1,356 @@
+use serde::ser::{self, Serialize};
+
+use super::{BluefoxData, BluefoxDataType, BluefoxError};
+
+// serde's data model maps onto notation values like this:
+// unit and none are null, sequences and tuples are arrays, maps and structs are data,
+// unit variants are the variant name, and other variants are data with the variant name as the only key
+pub fn to_data<T>(value: &T) -> Result<BluefoxData<'static>, BluefoxError> where T: Serialize + ?Sized {
+    match value.serialize(ValueSerializer)? {
+        BluefoxDataType::DATA(x) => Ok(x),
+        other => Err(BluefoxError::type_mismatch("", "data", &other.to_string()))
+    }
+}
+
+pub fn to_string<T>(value: &T) -> Result<String, BluefoxError> where T: Serialize + ?Sized {
+    Ok(to_data(value)?.to_string())
+}
+
+impl ser::Error for BluefoxError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        BluefoxError::Message(msg.to_string())
+    }
+}
+
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = BluefoxDataType<'static>;
+    type Error = BluefoxError;
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeArray;
+    type SerializeMap = SerializeData;
+    type SerializeStruct = SerializeData;
+    type SerializeStructVariant = SerializeData;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, BluefoxError> {
+        Ok(BluefoxDataType::BOOL(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, BluefoxError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, BluefoxError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, BluefoxError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, BluefoxError> {
+        Ok(BluefoxDataType::INT(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, BluefoxError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, BluefoxError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, BluefoxError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, BluefoxError> {
+        match i64::try_from(v) {
+            Ok(x) => self.serialize_i64(x),
+            Err(_) => Err(BluefoxError::type_mismatch("", "integer in the range of a 64 bit signed integer", &v.to_string()))
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, BluefoxError> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, BluefoxError> {
+        Ok(BluefoxDataType::FLOAT(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, BluefoxError> {
+        Ok(BluefoxDataType::STRING(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, BluefoxError> {
+        Ok(BluefoxDataType::STRING(v.to_owned()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, BluefoxError> {
+        Ok(BluefoxDataType::BYTES(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, BluefoxError> {
+        Ok(BluefoxDataType::NULL)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, BluefoxError> where T: Serialize + ?Sized {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, BluefoxError> {
+        Ok(BluefoxDataType::NULL)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, BluefoxError> {
+        Ok(BluefoxDataType::NULL)
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<Self::Ok, BluefoxError> {
+        Ok(BluefoxDataType::STRING(variant.to_owned()))
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Self::Ok, BluefoxError> where T: Serialize + ?Sized {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(self, _name: &'static str, _index: u32, variant: &'static str, value: &T) -> Result<Self::Ok, BluefoxError> where T: Serialize + ?Sized {
+        let mut data = BluefoxData::new();
+        data.data.insert(variant.to_owned(), value.serialize(self).map_err(|e| e.context(variant))?);
+        Ok(BluefoxDataType::DATA(data))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeArray, BluefoxError> {
+        Ok(SerializeArray {
+            items: Vec::with_capacity(len.unwrap_or(0)),
+            variant: None
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeArray, BluefoxError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SerializeArray, BluefoxError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, variant: &'static str, len: usize) -> Result<SerializeArray, BluefoxError> {
+        Ok(SerializeArray {
+            items: Vec::with_capacity(len),
+            variant: Some(variant)
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeData, BluefoxError> {
+        Ok(SerializeData {
+            data: BluefoxData::new(),
+            key: None,
+            variant: None
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeData, BluefoxError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, variant: &'static str, _len: usize) -> Result<SerializeData, BluefoxError> {
+        Ok(SerializeData {
+            data: BluefoxData::new(),
+            key: None,
+            variant: Some(variant)
+        })
+    }
+}
+
+// wraps the value of a tuple or struct variant in data keyed by the variant name
+fn wrap_variant(variant: Option<&'static str>, value: BluefoxDataType<'static>) -> BluefoxDataType<'static> {
+    match variant {
+        Some(x) => {
+            let mut data = BluefoxData::new();
+            data.data.insert(x.to_owned(), value);
+            BluefoxDataType::DATA(data)
+        },
+        None => value
+    }
+}
+
+struct SerializeArray {
+    items: Vec<BluefoxDataType<'static>>,
+    variant: Option<&'static str>
+}
+impl SerializeArray {
+    fn push<T>(&mut self, value: &T) -> Result<(), BluefoxError> where T: Serialize + ?Sized {
+        let index = format!("[{}]", self.items.len());
+        self.items.push(value.serialize(ValueSerializer).map_err(|e| e.context(&index))?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<BluefoxDataType<'static>, BluefoxError> {
+        Ok(wrap_variant(self.variant, BluefoxDataType::ARRAY(self.items)))
+    }
+}
+impl ser::SerializeSeq for SerializeArray {
+    type Ok = BluefoxDataType<'static>;
+    type Error = BluefoxError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), BluefoxError> where T: Serialize + ?Sized {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, BluefoxError> {
+        self.finish()
+    }
+}
+impl ser::SerializeTuple for SerializeArray {
+    type Ok = BluefoxDataType<'static>;
+    type Error = BluefoxError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), BluefoxError> where T: Serialize + ?Sized {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, BluefoxError> {
+        self.finish()
+    }
+}
+impl ser::SerializeTupleStruct for SerializeArray {
+    type Ok = BluefoxDataType<'static>;
+    type Error = BluefoxError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), BluefoxError> where T: Serialize + ?Sized {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, BluefoxError> {
+        self.finish()
+    }
+}
+impl ser::SerializeTupleVariant for SerializeArray {
+    type Ok = BluefoxDataType<'static>;
+    type Error = BluefoxError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), BluefoxError> where T: Serialize + ?Sized {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, BluefoxError> {
+        self.finish()
+    }
+}
+
+struct SerializeData {
+    data: BluefoxData<'static>,
+    key: Option<String>,
+    variant: Option<&'static str>
+}
+impl SerializeData {
+    fn insert<T>(&mut self, key: String, value: &T) -> Result<(), BluefoxError> where T: Serialize + ?Sized {
+        let value = value.serialize(ValueSerializer).map_err(|e| e.context(&key))?;
+        self.data.data.insert(key, value);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<BluefoxDataType<'static>, BluefoxError> {
+        Ok(wrap_variant(self.variant, BluefoxDataType::DATA(self.data)))
+    }
+}
+impl ser::SerializeMap for SerializeData {
+    type Ok = BluefoxDataType<'static>;
+    type Error = BluefoxError;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), BluefoxError> where T: Serialize + ?Sized {
+        // keys are always text in notation, so only values that read naturally as a key are allowed
+        let key = match key.serialize(ValueSerializer)? {
+            BluefoxDataType::STRING(x) => x,
+            BluefoxDataType::INT(x) => x.to_string(),
+            BluefoxDataType::BOOL(x) => x.to_string(),
+            other => return Err(BluefoxError::type_mismatch("", "string key", &other.to_string()))
+        };
+        self.key = Some(key);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), BluefoxError> where T: Serialize + ?Sized {
+        let key = self.key.take().ok_or_else(|| BluefoxError::Message("serialize_value called before serialize_key".to_owned()))?;
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, BluefoxError> {
+        self.finish()
+    }
+}
+impl ser::SerializeStruct for SerializeData {
+    type Ok = BluefoxDataType<'static>;
+    type Error = BluefoxError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), BluefoxError> where T: Serialize + ?Sized {
+        self.insert(key.to_owned(), value)
+    }
+
+    fn end(self) -> Result<Self::Ok, BluefoxError> {
+        self.finish()
+    }
+}
+impl ser::SerializeStructVariant for SerializeData {
+    type Ok = BluefoxDataType<'static>;
+    type Error = BluefoxError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), BluefoxError> where T: Serialize + ?Sized {
+        self.insert(key.to_owned(), value)
+    }
+
+    fn end(self) -> Result<Self::Ok, BluefoxError> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    enum Mode {
+        Fast,
+        Limited(u32),
+        Custom { name: String }
+    }
+
+    #[derive(Serialize)]
+    struct Server {
+        host: String,
+        port: u16,
+        tls: Option<bool>,
+        modes: Vec<Mode>,
+        weights: BTreeMap<String, f64>
+    }
+
+    #[test]
+    fn serialize() {
+        let server = Server {
+            host: "localhost".to_owned(),
+            port: 8080,
+            tls: None,
+            modes: vec![Mode::Fast, Mode::Limited(5), Mode::Custom { name: "x".to_owned() }],
+            weights: BTreeMap::from([("a".to_owned(), 0.5)])
+        };
+        assert_eq!(to_string(&server).unwrap(), "host: \"localhost\"
+port: 8080
+tls: null
+modes: [\"Fast\", { Limited: 5 }, { Custom: { name: \"x\" } }]
+weights: { a: 0.5 }
+");
+
+        assert!(to_data(&5).is_err());
+        match to_data(&BTreeMap::from([("big", u64::MAX)])) {
+            Err(BluefoxError::TypeMismatch { path, .. }) => assert_eq!(path, "big"),
+            other => panic!("expected type mismatch, got {:?}", other)
+        }
+    }
+}
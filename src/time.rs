@@ -0,0 +1,311 @@
+use std::{
+    fmt,
+    time::Duration
+};
+use mlua::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BluefoxDate {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BluefoxTime {
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub nanosecond: u32
+}
+
+// offset is in minutes east of utc, None means local time with no offset given
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BluefoxDateTime {
+    pub date: BluefoxDate,
+    pub time: BluefoxTime,
+    pub offset: Option<i16>
+}
+
+impl fmt::Display for BluefoxDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+impl fmt::Display for BluefoxTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}:{:02}", self.hour, self.minute, self.second)?;
+        if self.nanosecond > 0 {
+            let fraction = format!("{:09}", self.nanosecond);
+            write!(f, ".{}", fraction.trim_end_matches('0'))?;
+        }
+        Ok(())
+    }
+}
+impl fmt::Display for BluefoxDateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}T{}", self.date, self.time)?;
+        match self.offset {
+            None => Ok(()),
+            Some(0) => write!(f, "Z"),
+            Some(x) => write!(f, "{}{:02}:{:02}", if x < 0 { '-' } else { '+' }, x.abs() / 60, x.abs() % 60)
+        }
+    }
+}
+
+// largest units first, leftover nanoseconds in the largest sub-second unit that divides them exactly
+pub(crate) fn duration_to_string(value: &Duration) -> String {
+    let mut secs = value.as_secs();
+    let nanos = value.subsec_nanos();
+    if secs == 0 && nanos == 0 {
+        return "0s".to_owned();
+    }
+
+    let mut output = "".to_owned();
+    for (unit, size) in [("d", 86400), ("h", 3600), ("m", 60), ("s", 1)] {
+        if secs >= size {
+            output += &format!("{}{}", secs / size, unit);
+            secs %= size;
+        }
+    }
+    if nanos > 0 {
+        if nanos.is_multiple_of(1_000_000) {
+            output += &format!("{}ms", nanos / 1_000_000);
+        }
+        else if nanos.is_multiple_of(1_000) {
+            output += &format!("{}us", nanos / 1_000);
+        }
+        else {
+            output += &format!("{}ns", nanos);
+        }
+    }
+    output
+}
+
+fn fixed_digits(text: &str, len: usize) -> Option<u32> {
+    if text.len() != len || !text.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31
+    }
+}
+
+// like the number grammar, None means the text doesn't have the shape at all and
+// an error means it does but the value is out of range
+pub(crate) fn parse_date(text: &str) -> Option<Result<BluefoxDate, &'static str>> {
+    let year = fixed_digits(text.get(0..4)?, 4)?;
+    let month = fixed_digits(text.get(5..7)?, 2)?;
+    let day = fixed_digits(text.get(8..)?, 2)?;
+    if &text[4..5] != "-" || &text[7..8] != "-" {
+        return None;
+    }
+    let (year, month, day) = (year as u16, month as u8, day as u8);
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return Some(Err("valid date"));
+    }
+    Some(Ok(BluefoxDate { year, month, day }))
+}
+
+pub(crate) fn parse_time(text: &str) -> Option<Result<BluefoxTime, &'static str>> {
+    let hour = fixed_digits(text.get(0..2)?, 2)?;
+    let minute = fixed_digits(text.get(3..5)?, 2)?;
+    let (second, fraction) = match text.get(6..)?.split_once('.') {
+        Some((s, f)) => (s, Some(f)),
+        None => (text.get(6..)?, None)
+    };
+    let second = fixed_digits(second, 2)?;
+    if &text[2..3] != ":" || &text[5..6] != ":" {
+        return None;
+    }
+    let nanosecond = match fraction {
+        Some(f) => {
+            if f.is_empty() || f.len() > 9 || !f.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            f.parse::<u32>().ok()? * 10u32.pow(9 - f.len() as u32)
+        },
+        None => 0
+    };
+    if hour > 23 || minute > 59 || second > 59 {
+        return Some(Err("valid time"));
+    }
+    Some(Ok(BluefoxTime {
+        hour: hour as u8,
+        minute: minute as u8,
+        second: second as u8,
+        nanosecond
+    }))
+}
+
+pub(crate) fn parse_datetime(text: &str) -> Option<Result<BluefoxDateTime, &'static str>> {
+    if text.get(10..11)? != "T" {
+        return None;
+    }
+    let rest = &text[11..];
+    let (time, offset) = if let Some(time) = rest.strip_suffix('Z') {
+        (time, Some(0))
+    }
+    else if let Some(offset) = rest.len().checked_sub(6).and_then(|i| rest.get(i..)).filter(|x| x.starts_with(['+', '-'])) {
+        let hours = fixed_digits(offset.get(1..3)?, 2)? as i16;
+        let minutes = fixed_digits(offset.get(4..)?, 2)? as i16;
+        if &offset[3..4] != ":" {
+            return None;
+        }
+        if hours > 23 || minutes > 59 {
+            return Some(Err("valid utc offset"));
+        }
+        let sign = if offset.starts_with('-') { -1 } else { 1 };
+        (&rest[..rest.len() - 6], Some(sign * (hours * 60 + minutes)))
+    }
+    else {
+        (rest, None)
+    };
+    let date = match parse_date(&text[..10])? {
+        Ok(x) => x,
+        Err(e) => return Some(Err(e))
+    };
+    let time = match parse_time(time)? {
+        Ok(x) => x,
+        Err(e) => return Some(Err(e))
+    };
+    Some(Ok(BluefoxDateTime { date, time, offset }))
+}
+
+// durations are one or more whole numbers each followed by a unit, like 30s or 1h30m
+pub(crate) fn parse_duration(text: &str) -> Option<Result<Duration, &'static str>> {
+    let mut rest = text;
+    let mut total: u128 = 0;
+    if rest.is_empty() {
+        return None;
+    }
+    while !rest.is_empty() {
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 {
+            return None;
+        }
+        let amount = match rest[..digits].parse::<u128>() {
+            Ok(x) => x,
+            Err(_) => return Some(Err("duration in range"))
+        };
+        rest = &rest[digits..];
+        let (unit, size) = [("ns", 1), ("us", 1_000), ("ms", 1_000_000), ("s", 1_000_000_000), ("m", 60_000_000_000), ("h", 3_600_000_000_000), ("d", 86_400_000_000_000)]
+            .into_iter()
+            .find(|(unit, _)| rest.starts_with(unit) && rest[unit.len()..].chars().next().is_none_or(|c| c.is_ascii_digit()))?;
+        rest = &rest[unit.len()..];
+        total = match amount.checked_mul(size).and_then(|x| x.checked_add(total)) {
+            Some(x) => x,
+            None => return Some(Err("duration in range"))
+        };
+    }
+    let secs = total / 1_000_000_000;
+    if secs > u64::MAX as u128 {
+        return Some(Err("duration in range"));
+    }
+    Some(Ok(Duration::new(secs as u64, (total % 1_000_000_000) as u32)))
+}
+
+impl LuaUserData for BluefoxDate {
+    fn add_fields<'lua, F: LuaUserDataFields<'lua, Self>>(fields: &mut F) {
+        fields.add_field_method_get("year", |_, this| Ok(this.year));
+        fields.add_field_method_get("month", |_, this| Ok(this.month));
+        fields.add_field_method_get("day", |_, this| Ok(this.day));
+    }
+
+    fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, ()| Ok(this.to_string()));
+        methods.add_meta_method(LuaMetaMethod::Eq, |_, this, other: LuaUserDataRef<Self>| Ok(*this == *other));
+        methods.add_meta_method(LuaMetaMethod::Lt, |_, this, other: LuaUserDataRef<Self>| Ok(*this < *other));
+        methods.add_meta_method(LuaMetaMethod::Le, |_, this, other: LuaUserDataRef<Self>| Ok(*this <= *other));
+    }
+}
+impl LuaUserData for BluefoxTime {
+    fn add_fields<'lua, F: LuaUserDataFields<'lua, Self>>(fields: &mut F) {
+        fields.add_field_method_get("hour", |_, this| Ok(this.hour));
+        fields.add_field_method_get("minute", |_, this| Ok(this.minute));
+        fields.add_field_method_get("second", |_, this| Ok(this.second));
+        fields.add_field_method_get("nanosecond", |_, this| Ok(this.nanosecond));
+    }
+
+    fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, ()| Ok(this.to_string()));
+        methods.add_meta_method(LuaMetaMethod::Eq, |_, this, other: LuaUserDataRef<Self>| Ok(*this == *other));
+        methods.add_meta_method(LuaMetaMethod::Lt, |_, this, other: LuaUserDataRef<Self>| Ok(*this < *other));
+        methods.add_meta_method(LuaMetaMethod::Le, |_, this, other: LuaUserDataRef<Self>| Ok(*this <= *other));
+    }
+}
+impl LuaUserData for BluefoxDateTime {
+    fn add_fields<'lua, F: LuaUserDataFields<'lua, Self>>(fields: &mut F) {
+        fields.add_field_method_get("date", |_, this| Ok(this.date));
+        fields.add_field_method_get("time", |_, this| Ok(this.time));
+        fields.add_field_method_get("offset", |_, this| Ok(this.offset));
+    }
+
+    fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, ()| Ok(this.to_string()));
+        methods.add_meta_method(LuaMetaMethod::Eq, |_, this, other: LuaUserDataRef<Self>| Ok(*this == *other));
+    }
+}
+
+// std's Duration can't implement lua traits here, so it crosses into lua wrapped
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct LuaDuration(pub Duration);
+impl LuaUserData for LuaDuration {
+    fn add_fields<'lua, F: LuaUserDataFields<'lua, Self>>(fields: &mut F) {
+        fields.add_field_method_get("seconds", |_, this| Ok(this.0.as_secs_f64()));
+    }
+
+    fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, ()| Ok(duration_to_string(&this.0)));
+        methods.add_meta_method(LuaMetaMethod::Eq, |_, this, other: LuaUserDataRef<Self>| Ok(this.0 == other.0));
+        methods.add_meta_method(LuaMetaMethod::Lt, |_, this, other: LuaUserDataRef<Self>| Ok(this.0 < other.0));
+        methods.add_meta_method(LuaMetaMethod::Le, |_, this, other: LuaUserDataRef<Self>| Ok(this.0 <= other.0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("30s"), Some(Ok(Duration::from_secs(30))));
+        assert_eq!(parse_duration("1h30m"), Some(Ok(Duration::from_secs(5400))));
+        assert_eq!(parse_duration("1500ms"), Some(Ok(Duration::from_millis(1500))));
+        assert_eq!(parse_duration("2d3us"), Some(Ok(Duration::new(172800, 3000))));
+        assert_eq!(parse_duration("30"), None);
+        assert_eq!(parse_duration("30 s"), None);
+        assert_eq!(parse_duration("5 minutes"), None);
+        assert_eq!(parse_duration("5mss"), None);
+        assert_eq!(parse_duration("99999999999999999999999999999999999999999d"), Some(Err("duration in range")));
+
+        for text in ["0s", "30s", "1h30m", "1d1h1m1s", "1500ms", "1s5us", "7ns"] {
+            let duration = parse_duration(text).unwrap().unwrap();
+            assert_eq!(parse_duration(&duration_to_string(&duration)), Some(Ok(duration)));
+        }
+        assert_eq!(duration_to_string(&Duration::from_secs(5400)), "1h30m");
+    }
+
+    #[test]
+    fn dates_and_times() {
+        assert_eq!(parse_date("2024-02-29"), Some(Ok(BluefoxDate { year: 2024, month: 2, day: 29 })));
+        assert_eq!(parse_date("2023-02-29"), Some(Err("valid date")));
+        assert_eq!(parse_date("2024-1-1"), None);
+        assert_eq!(parse_time("23:59:01.5").unwrap().unwrap().nanosecond, 500_000_000);
+        assert_eq!(parse_time("24:00:00"), Some(Err("valid time")));
+
+        let datetime = parse_datetime("2024-01-01T08:30:00+05:30").unwrap().unwrap();
+        assert_eq!(datetime.offset, Some(330));
+        assert_eq!(datetime.to_string(), "2024-01-01T08:30:00+05:30");
+        assert_eq!(parse_datetime("2024-01-01T00:00:00Z").unwrap().unwrap().offset, Some(0));
+        assert_eq!(parse_datetime("2024-01-01T00:00:00.25").unwrap().unwrap().to_string(), "2024-01-01T00:00:00.25");
+        assert_eq!(parse_datetime("2024-01-01 00:00:00"), None);
+    }
+}
@@ -0,0 +1,608 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::BuildHasher,
+    path::PathBuf
+};
+
+use super::{BluefoxData, BluefoxDataType, BluefoxDeserialize, BluefoxError, BluefoxSerialize};
+
+// conversions for a single value, used for the fields of types that derive BluefoxSerialize and BluefoxDeserialize
+pub trait IntoBluefoxValue<'a> {
+    // fails for values notation can't hold, like a u64 past i64::MAX
+    fn into_value(self) -> Result<BluefoxDataType<'a>, BluefoxError>;
+}
+
+pub trait FromBluefoxValue<'a>: Sized {
+    fn from_value(value: BluefoxDataType<'a>) -> Result<Self, BluefoxError>;
+}
+
+impl BluefoxDataType<'_> {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            BluefoxDataType::NULL => "null",
+            BluefoxDataType::BOOL(_) => "bool",
+            BluefoxDataType::INT(_) => "int",
+            BluefoxDataType::FLOAT(_) => "float",
+            BluefoxDataType::STRING(_) => "string",
+            BluefoxDataType::DATETIME(_) => "datetime",
+            BluefoxDataType::DATE(_) => "date",
+            BluefoxDataType::TIME(_) => "time",
+            BluefoxDataType::DURATION(_) => "duration",
+            BluefoxDataType::BYTES(_) => "bytes",
+            BluefoxDataType::FUNCTION(..) => "function",
+            BluefoxDataType::ARRAY(_) => "array",
+            BluefoxDataType::DATA(_) => "data"
+        }
+    }
+}
+impl <'a> BluefoxDataType<'a> {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            BluefoxDataType::BOOL(x) => Some(*x),
+            _ => None
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            BluefoxDataType::INT(x) => Some(*x),
+            _ => None
+        }
+    }
+
+    // ints count as floats, same as reading an f64
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            BluefoxDataType::FLOAT(x) => Some(*x),
+            BluefoxDataType::INT(x) => Some(*x as f64),
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            BluefoxDataType::STRING(x) => Some(x),
+            _ => None
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<BluefoxDataType<'a>>> {
+        match self {
+            BluefoxDataType::ARRAY(x) => Some(x),
+            _ => None
+        }
+    }
+
+    pub fn as_data(&self) -> Option<&BluefoxData<'a>> {
+        match self {
+            BluefoxDataType::DATA(x) => Some(x),
+            _ => None
+        }
+    }
+
+    // the lua source of a function
+    pub fn as_function(&self) -> Option<&str> {
+        match self {
+            BluefoxDataType::FUNCTION(x, _) => Some(x),
+            _ => None
+        }
+    }
+}
+
+fn mismatch(expected: &str, found: &BluefoxDataType) -> BluefoxError {
+    BluefoxError::type_mismatch("", expected, found.type_name())
+}
+
+fn out_of_range(kind: &str, target: &str, value: &str) -> BluefoxError {
+    BluefoxError::type_mismatch("", &format!("{} in the range of {}", kind, target), value)
+}
+
+impl <'a> IntoBluefoxValue<'a> for BluefoxDataType<'a> {
+    fn into_value(self) -> Result<BluefoxDataType<'a>, BluefoxError> {
+        Ok(self)
+    }
+}
+impl <'a> FromBluefoxValue<'a> for BluefoxDataType<'a> {
+    fn from_value(value: BluefoxDataType<'a>) -> Result<Self, BluefoxError> {
+        Ok(value)
+    }
+}
+
+impl <'a> IntoBluefoxValue<'a> for BluefoxData<'a> {
+    fn into_value(self) -> Result<BluefoxDataType<'a>, BluefoxError> {
+        Ok(BluefoxDataType::DATA(self))
+    }
+}
+impl <'a> FromBluefoxValue<'a> for BluefoxData<'a> {
+    fn from_value(value: BluefoxDataType<'a>) -> Result<Self, BluefoxError> {
+        match value {
+            BluefoxDataType::DATA(x) => Ok(x),
+            other => Err(mismatch("data", &other))
+        }
+    }
+}
+
+impl <'a> IntoBluefoxValue<'a> for bool {
+    fn into_value(self) -> Result<BluefoxDataType<'a>, BluefoxError> {
+        Ok(BluefoxDataType::BOOL(self))
+    }
+}
+impl <'a> FromBluefoxValue<'a> for bool {
+    fn from_value(value: BluefoxDataType<'a>) -> Result<Self, BluefoxError> {
+        match value {
+            BluefoxDataType::BOOL(x) => Ok(x),
+            other => Err(mismatch("bool", &other))
+        }
+    }
+}
+
+// integers are stored as i64, reading into a smaller type checks that the value fits
+macro_rules! integer {
+    ($($t:ty),*) => {
+        $(
+            impl <'a> IntoBluefoxValue<'a> for $t {
+                fn into_value(self) -> Result<BluefoxDataType<'a>, BluefoxError> {
+                    Ok(BluefoxDataType::INT(self as i64))
+                }
+            }
+            impl <'a> FromBluefoxValue<'a> for $t {
+                fn from_value(value: BluefoxDataType<'a>) -> Result<Self, BluefoxError> {
+                    match value {
+                        BluefoxDataType::INT(x) => <$t>::try_from(x).map_err(|_| out_of_range("int", stringify!($t), &x.to_string())),
+                        other => Err(mismatch("int", &other))
+                    }
+                }
+            }
+        )*
+    };
+}
+integer!(i8, i16, i32, i64, u8, u16, u32);
+
+// the unsigned types that can go past i64::MAX, writing one that does is an error
+macro_rules! wide_integer {
+    ($($t:ty),*) => {
+        $(
+            impl <'a> IntoBluefoxValue<'a> for $t {
+                fn into_value(self) -> Result<BluefoxDataType<'a>, BluefoxError> {
+                    i64::try_from(self).map(BluefoxDataType::INT).map_err(|_| out_of_range("int", "i64", &self.to_string()))
+                }
+            }
+            impl <'a> FromBluefoxValue<'a> for $t {
+                fn from_value(value: BluefoxDataType<'a>) -> Result<Self, BluefoxError> {
+                    match value {
+                        BluefoxDataType::INT(x) => <$t>::try_from(x).map_err(|_| out_of_range("int", stringify!($t), &x.to_string())),
+                        other => Err(mismatch("int", &other))
+                    }
+                }
+            }
+        )*
+    };
+}
+wide_integer!(u64, usize);
+
+impl <'a> IntoBluefoxValue<'a> for isize {
+    fn into_value(self) -> Result<BluefoxDataType<'a>, BluefoxError> {
+        Ok(BluefoxDataType::INT(self as i64))
+    }
+}
+impl <'a> FromBluefoxValue<'a> for isize {
+    fn from_value(value: BluefoxDataType<'a>) -> Result<Self, BluefoxError> {
+        match value {
+            BluefoxDataType::INT(x) => isize::try_from(x).map_err(|_| out_of_range("int", "isize", &x.to_string())),
+            other => Err(mismatch("int", &other))
+        }
+    }
+}
+
+impl <'a> IntoBluefoxValue<'a> for f64 {
+    fn into_value(self) -> Result<BluefoxDataType<'a>, BluefoxError> {
+        Ok(BluefoxDataType::FLOAT(self))
+    }
+}
+impl <'a> FromBluefoxValue<'a> for f64 {
+    // ints are accepted too since a float like 2.0 may well be written as 2
+    fn from_value(value: BluefoxDataType<'a>) -> Result<Self, BluefoxError> {
+        match value {
+            BluefoxDataType::FLOAT(x) => Ok(x),
+            BluefoxDataType::INT(x) => Ok(x as f64),
+            other => Err(mismatch("float", &other))
+        }
+    }
+}
+
+impl <'a> IntoBluefoxValue<'a> for f32 {
+    fn into_value(self) -> Result<BluefoxDataType<'a>, BluefoxError> {
+        Ok(BluefoxDataType::FLOAT(self as f64))
+    }
+}
+impl <'a> FromBluefoxValue<'a> for f32 {
+    // precision is allowed to be lost, but a finite value too large for f32 is not
+    fn from_value(value: BluefoxDataType<'a>) -> Result<Self, BluefoxError> {
+        let x = f64::from_value(value)?;
+        if x.is_finite() && x.abs() > f32::MAX as f64 {
+            return Err(out_of_range("float", "f32", &x.to_string()));
+        }
+        Ok(x as f32)
+    }
+}
+
+impl <'a> IntoBluefoxValue<'a> for char {
+    fn into_value(self) -> Result<BluefoxDataType<'a>, BluefoxError> {
+        Ok(BluefoxDataType::STRING(self.to_string()))
+    }
+}
+impl <'a> FromBluefoxValue<'a> for char {
+    fn from_value(value: BluefoxDataType<'a>) -> Result<Self, BluefoxError> {
+        let x = String::from_value(value)?;
+        let mut chars = x.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(BluefoxError::type_mismatch("", "single character", &format!("\"{}\"", x)))
+        }
+    }
+}
+
+impl <'a> IntoBluefoxValue<'a> for String {
+    fn into_value(self) -> Result<BluefoxDataType<'a>, BluefoxError> {
+        Ok(BluefoxDataType::STRING(self))
+    }
+}
+impl <'a> FromBluefoxValue<'a> for String {
+    fn from_value(value: BluefoxDataType<'a>) -> Result<Self, BluefoxError> {
+        match value {
+            BluefoxDataType::STRING(x) => Ok(x),
+            other => Err(mismatch("string", &other))
+        }
+    }
+}
+
+impl <'a> IntoBluefoxValue<'a> for &str {
+    fn into_value(self) -> Result<BluefoxDataType<'a>, BluefoxError> {
+        Ok(BluefoxDataType::STRING(self.to_owned()))
+    }
+}
+
+impl <'a> IntoBluefoxValue<'a> for PathBuf {
+    fn into_value(self) -> Result<BluefoxDataType<'a>, BluefoxError> {
+        Ok(BluefoxDataType::STRING(self.to_string_lossy().into_owned()))
+    }
+}
+impl <'a> FromBluefoxValue<'a> for PathBuf {
+    fn from_value(value: BluefoxDataType<'a>) -> Result<Self, BluefoxError> {
+        Ok(PathBuf::from(String::from_value(value)?))
+    }
+}
+
+impl <'a, T> IntoBluefoxValue<'a> for Box<T> where T: IntoBluefoxValue<'a> {
+    fn into_value(self) -> Result<BluefoxDataType<'a>, BluefoxError> {
+        (*self).into_value()
+    }
+}
+impl <'a, T> FromBluefoxValue<'a> for Box<T> where T: FromBluefoxValue<'a> {
+    fn from_value(value: BluefoxDataType<'a>) -> Result<Self, BluefoxError> {
+        Ok(Box::new(T::from_value(value)?))
+    }
+}
+
+// none is null, and null is none, so optional keys can also be left out of a derived struct
+impl <'a, T> IntoBluefoxValue<'a> for Option<T> where T: IntoBluefoxValue<'a> {
+    fn into_value(self) -> Result<BluefoxDataType<'a>, BluefoxError> {
+        match self {
+            Some(x) => x.into_value(),
+            None => Ok(BluefoxDataType::NULL)
+        }
+    }
+}
+impl <'a, T> FromBluefoxValue<'a> for Option<T> where T: FromBluefoxValue<'a> {
+    fn from_value(value: BluefoxDataType<'a>) -> Result<Self, BluefoxError> {
+        match value {
+            BluefoxDataType::NULL => Ok(None),
+            other => Ok(Some(T::from_value(other)?))
+        }
+    }
+}
+
+impl <'a, T> IntoBluefoxValue<'a> for Vec<T> where T: IntoBluefoxValue<'a> {
+    fn into_value(self) -> Result<BluefoxDataType<'a>, BluefoxError> {
+        into_array(self)
+    }
+}
+impl <'a, T> FromBluefoxValue<'a> for Vec<T> where T: FromBluefoxValue<'a> {
+    fn from_value(value: BluefoxDataType<'a>) -> Result<Self, BluefoxError> {
+        let mut output = vec![];
+        for (i, v) in array(value)?.into_iter().enumerate() {
+            output.push(T::from_value(v).map_err(|e| e.context(&format!("[{}]", i)))?);
+        }
+        Ok(output)
+    }
+}
+
+fn into_array<'a, T>(items: impl IntoIterator<Item = T>) -> Result<BluefoxDataType<'a>, BluefoxError> where T: IntoBluefoxValue<'a> {
+    let mut output = vec![];
+    for (i, v) in items.into_iter().enumerate() {
+        output.push(v.into_value().map_err(|e| e.context(&format!("[{}]", i)))?);
+    }
+    Ok(BluefoxDataType::ARRAY(output))
+}
+
+fn array(value: BluefoxDataType) -> Result<Vec<BluefoxDataType>, BluefoxError> {
+    match value {
+        BluefoxDataType::ARRAY(x) => Ok(x),
+        other => Err(mismatch("array", &other))
+    }
+}
+
+fn length_mismatch(expected: usize, found: usize) -> BluefoxError {
+    BluefoxError::type_mismatch("", &format!("array of length {}", expected), &format!("array of length {}", found))
+}
+
+impl <'a, T, const N: usize> IntoBluefoxValue<'a> for [T; N] where T: IntoBluefoxValue<'a> {
+    fn into_value(self) -> Result<BluefoxDataType<'a>, BluefoxError> {
+        into_array(self)
+    }
+}
+impl <'a, T, const N: usize> FromBluefoxValue<'a> for [T; N] where T: FromBluefoxValue<'a> {
+    fn from_value(value: BluefoxDataType<'a>) -> Result<Self, BluefoxError> {
+        let items = Vec::<T>::from_value(value)?;
+        let len = items.len();
+        items.try_into().map_err(|_| length_mismatch(N, len))
+    }
+}
+
+// tuples are arrays with one item per element
+macro_rules! tuple {
+    ($len:expr => $($name:ident $index:tt),*) => {
+        impl <'a, $($name),*> IntoBluefoxValue<'a> for ($($name,)*) where $($name: IntoBluefoxValue<'a>),* {
+            fn into_value(self) -> Result<BluefoxDataType<'a>, BluefoxError> {
+                Ok(BluefoxDataType::ARRAY(vec![$(self.$index.into_value().map_err(|e| e.context(concat!("[", $index, "]")))?),*]))
+            }
+        }
+        impl <'a, $($name),*> FromBluefoxValue<'a> for ($($name,)*) where $($name: FromBluefoxValue<'a>),* {
+            fn from_value(value: BluefoxDataType<'a>) -> Result<Self, BluefoxError> {
+                let items = array(value)?;
+                if items.len() != $len {
+                    return Err(length_mismatch($len, items.len()));
+                }
+                let mut items = items.into_iter();
+                Ok(($($name::from_value(items.next().unwrap()).map_err(|e| e.context(concat!("[", $index, "]")))?,)*))
+            }
+        }
+    };
+}
+tuple!(1 => A 0);
+tuple!(2 => A 0, B 1);
+tuple!(3 => A 0, B 1, C 2);
+tuple!(4 => A 0, B 1, C 2, D 3);
+tuple!(5 => A 0, B 1, C 2, D 3, E 4);
+tuple!(6 => A 0, B 1, C 2, D 3, E 4, F 5);
+
+fn entries<'a, V>(data: BluefoxData<'a>) -> Result<Vec<(String, V)>, BluefoxError> where V: FromBluefoxValue<'a> {
+    let mut output = vec![];
+    for (k, v) in data.data {
+        let v = V::from_value(v).map_err(|e| e.context(&k))?;
+        output.push((k, v));
+    }
+    Ok(output)
+}
+
+fn into_data<'a, V>(items: impl IntoIterator<Item = (String, V)>) -> Result<BluefoxData<'a>, BluefoxError> where V: IntoBluefoxValue<'a> {
+    let mut output = BluefoxData::new();
+    for (k, v) in items {
+        let v = v.into_value().map_err(|e| e.context(&k))?;
+        output.data.insert(k, v);
+    }
+    Ok(output)
+}
+
+// to_data can't return an error, so it panics where try_to_data would have returned one
+fn expect_data(data: Result<BluefoxData, BluefoxError>) -> BluefoxData {
+    match data {
+        Ok(x) => x,
+        Err(e) => panic!("value can't be written as notation: {}", e)
+    }
+}
+
+// a hash map has no order of its own, so its keys are written sorted to keep the output stable
+impl <'a, V, S> BluefoxSerialize<'a> for HashMap<String, V, S> where V: IntoBluefoxValue<'a> {
+    fn to_data(self) -> BluefoxData<'a> {
+        expect_data(self.try_to_data())
+    }
+
+    fn try_to_data(self) -> Result<BluefoxData<'a>, BluefoxError> {
+        let mut items: Vec<(String, V)> = self.into_iter().collect();
+        items.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+        into_data(items)
+    }
+}
+impl <'a, V, S> BluefoxDeserialize<'a> for HashMap<String, V, S> where V: FromBluefoxValue<'a>, S: BuildHasher + Default {
+    fn from_data(data: BluefoxData<'a>) -> Result<Self, BluefoxError> {
+        Ok(entries(data)?.into_iter().collect())
+    }
+}
+impl <'a, V, S> IntoBluefoxValue<'a> for HashMap<String, V, S> where V: IntoBluefoxValue<'a> {
+    fn into_value(self) -> Result<BluefoxDataType<'a>, BluefoxError> {
+        self.try_to_data().map(BluefoxDataType::DATA)
+    }
+}
+impl <'a, V, S> FromBluefoxValue<'a> for HashMap<String, V, S> where V: FromBluefoxValue<'a>, S: BuildHasher + Default {
+    fn from_value(value: BluefoxDataType<'a>) -> Result<Self, BluefoxError> {
+        Self::from_data(BluefoxData::from_value(value)?)
+    }
+}
+
+impl <'a, V> BluefoxSerialize<'a> for BTreeMap<String, V> where V: IntoBluefoxValue<'a> {
+    fn to_data(self) -> BluefoxData<'a> {
+        expect_data(self.try_to_data())
+    }
+
+    fn try_to_data(self) -> Result<BluefoxData<'a>, BluefoxError> {
+        into_data(self)
+    }
+}
+impl <'a, V> BluefoxDeserialize<'a> for BTreeMap<String, V> where V: FromBluefoxValue<'a> {
+    fn from_data(data: BluefoxData<'a>) -> Result<Self, BluefoxError> {
+        Ok(entries(data)?.into_iter().collect())
+    }
+}
+impl <'a, V> IntoBluefoxValue<'a> for BTreeMap<String, V> where V: IntoBluefoxValue<'a> {
+    fn into_value(self) -> Result<BluefoxDataType<'a>, BluefoxError> {
+        self.try_to_data().map(BluefoxDataType::DATA)
+    }
+}
+impl <'a, V> FromBluefoxValue<'a> for BTreeMap<String, V> where V: FromBluefoxValue<'a> {
+    fn from_value(value: BluefoxDataType<'a>) -> Result<Self, BluefoxError> {
+        Self::from_data(BluefoxData::from_value(value)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values() {
+        assert_eq!(f64::from_value(BluefoxDataType::INT(2)).unwrap(), 2.0);
+        assert_eq!(String::from_value("x".to_owned().into_value().unwrap()).unwrap(), "x");
+        match bool::from_value(BluefoxDataType::INT(1)) {
+            Err(BluefoxError::TypeMismatch { expected, found, .. }) => assert_eq!((expected.as_str(), found.as_str()), ("bool", "int")),
+            other => panic!("expected type mismatch, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn accessors() {
+        let data = BluefoxData::try_from("a: true\nb: 2\nc: text\nd: [1]\ne: {}\nf: `return 1`\ng: { h: 70000 }").unwrap();
+        assert_eq!(data["a".to_owned()].as_bool(), Some(true));
+        assert_eq!(data["b".to_owned()].as_i64(), Some(2));
+        assert_eq!(data["b".to_owned()].as_f64(), Some(2.0));
+        assert_eq!(data["b".to_owned()].as_str(), None);
+        assert_eq!(data["c".to_owned()].as_str(), Some("text"));
+        assert_eq!(data["d".to_owned()].as_array(), Some(&vec![BluefoxDataType::INT(1)]));
+        assert_eq!(data["e".to_owned()].as_data(), Some(&BluefoxData::new()));
+        assert_eq!(data["f".to_owned()].as_function(), Some("return 1"));
+
+        assert_eq!(data.get_as::<u32>("g.h").unwrap(), 70000);
+        assert_eq!(data.get_as::<Option<String>>("c").unwrap(), Some("text".to_owned()));
+        assert_eq!(data.get_as::<u16>("g.h").unwrap_err().to_string(), "g.h: expected int in the range of u16, found 70000");
+        assert_eq!(data.get_as::<String>("d").unwrap_err().to_string(), "d: expected string, found array");
+        assert_eq!(data.get_as::<i64>("d[0]").unwrap(), 1);
+        match data.get_as::<bool>("g.missing") {
+            Err(BluefoxError::MissingKey { path }) => assert_eq!(path, "g.missing"),
+            other => panic!("expected missing key, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn std_values() {
+        assert_eq!(u16::from_value(BluefoxDataType::INT(8080)).unwrap(), 8080);
+        assert_eq!(u16::from_value(BluefoxDataType::INT(70000)).unwrap_err().to_string(), "expected int in the range of u16, found 70000");
+        assert!(u8::from_value(BluefoxDataType::INT(-1)).is_err());
+        assert_eq!(f32::from_value(BluefoxDataType::FLOAT(1e39)).unwrap_err().to_string(), "expected float in the range of f32, found 1000000000000000000000000000000000000000");
+        assert_eq!(u64::from_value((i64::MAX as u64).into_value().unwrap()).unwrap(), i64::MAX as u64);
+        match u64::MAX.into_value() {
+            Err(BluefoxError::TypeMismatch { expected, found, .. }) => assert_eq!((expected.as_str(), found.as_str()), ("int in the range of i64", "18446744073709551615")),
+            other => panic!("expected type mismatch, got {:?}", other)
+        }
+        assert!(u64::from_value(BluefoxDataType::FLOAT(1e19)).is_err());
+        match BTreeMap::from([("ids".to_owned(), vec![1, usize::MAX])]).try_to_data() {
+            Err(BluefoxError::TypeMismatch { path, .. }) => assert_eq!(path, "ids[1]"),
+            other => panic!("expected type mismatch, got {:?}", other)
+        }
+
+        assert_eq!(Option::<u16>::from_value(BluefoxDataType::NULL).unwrap(), None);
+        assert_eq!(Some(3u16).into_value().unwrap(), BluefoxDataType::INT(3));
+        assert_eq!(<(i32, i32)>::from_value((1, 2).into_value().unwrap()).unwrap(), (1, 2));
+        assert_eq!(<[char; 2]>::from_value(['a', 'b'].into_value().unwrap()).unwrap(), ['a', 'b']);
+        assert!(<[char; 3]>::from_value(['a', 'b'].into_value().unwrap()).is_err());
+        assert_eq!(Box::<PathBuf>::from_value(PathBuf::from("a/b").into_value().unwrap()).unwrap(), Box::new(PathBuf::from("a/b")));
+
+        let names = vec!["a".to_owned(), "b".to_owned()];
+        assert_eq!(Vec::<String>::from_value(names.clone().into_value().unwrap()).unwrap(), names);
+        match Vec::<u8>::from_value(vec![1, 300].into_value().unwrap()) {
+            Err(BluefoxError::TypeMismatch { path, .. }) => assert_eq!(path, "[1]"),
+            other => panic!("expected type mismatch, got {:?}", other)
+        }
+
+        let weights = HashMap::from([("b".to_owned(), 0.5f32), ("a".to_owned(), 2.0)]);
+        assert_eq!(BluefoxData::to_string(weights.clone()), "a: 2.0\nb: 0.5\n");
+        let data = BluefoxData::try_from("a: 2\nb: 0.5").unwrap();
+        assert_eq!(HashMap::<String, f32>::from_data(data.clone()).unwrap(), weights);
+        assert_eq!(BTreeMap::<String, f32>::from_data(data).unwrap(), weights.into_iter().collect());
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive() {
+        use crate::{BluefoxDeserialize, BluefoxSerialize};
+
+        #[derive(Debug, PartialEq, BluefoxSerialize, BluefoxDeserialize)]
+        struct Limits {
+            low: i64,
+            high: i64
+        }
+
+        #[derive(Debug, PartialEq, BluefoxSerialize, BluefoxDeserialize)]
+        struct Extra {
+            ratio: f64
+        }
+
+        fn default_name() -> String {
+            "unnamed".to_owned()
+        }
+
+        #[derive(Debug, PartialEq, BluefoxSerialize, BluefoxDeserialize)]
+        struct Settings {
+            #[bluefox(default = "default_name")]
+            name: String,
+            #[bluefox(rename = "max")]
+            limit: i64,
+            #[bluefox(default)]
+            verbose: bool,
+            #[bluefox(skip)]
+            cache: Vec<u8>,
+            #[bluefox(flatten)]
+            extra: Extra,
+            #[bluefox(function)]
+            on_load: String,
+            limits: Limits
+        }
+
+        let settings = Settings {
+            name: "main".to_owned(),
+            limit: 4,
+            verbose: false,
+            cache: vec![1],
+            extra: Extra { ratio: 0.5 },
+            on_load: "return 1".to_owned(),
+            limits: Limits { low: 1, high: 2 }
+        };
+        let text = BluefoxData::to_string(Settings { cache: vec![], ..settings });
+        assert_eq!(text, "name: \"main\"\nmax: 4\nverbose: false\nratio: 0.5\non_load: `return 1`\nlimits: { low: 1, high: 2 }\n");
+
+        let data = BluefoxData::try_from("max: 4\nratio: 0.5\non_load: `return 1`\nlimits: { low: 1, high: 2 }").unwrap();
+        let read = Settings::from_data(data).unwrap();
+        assert_eq!(read.name, "unnamed");
+        assert_eq!(read.limits, Limits { low: 1, high: 2 });
+        assert!(read.cache.is_empty());
+
+        let data = BluefoxData::try_from("max: 4\nratio: 0.5\non_load: `return 1`\nlimits: { low: 1, high: true }").unwrap();
+        match Settings::from_data(data) {
+            Err(BluefoxError::TypeMismatch { path, .. }) => assert_eq!(path, "limits.high"),
+            other => panic!("expected type mismatch, got {:?}", other)
+        }
+        let data = BluefoxData::try_from("ratio: 0.5\non_load: `return 1`\nlimits: { low: 1, high: 2 }").unwrap();
+        match Settings::from_data(data) {
+            Err(BluefoxError::MissingKey { path }) => assert_eq!(path, "max"),
+            other => panic!("expected missing key, got {:?}", other)
+        }
+
+        #[derive(BluefoxSerialize)]
+        struct Counter {
+            hits: u64
+        }
+        match (Counter { hits: u64::MAX }).try_to_data() {
+            Err(BluefoxError::TypeMismatch { path, .. }) => assert_eq!(path, "hits"),
+            other => panic!("expected type mismatch, got {:?}", other.map(|x| x.to_string()))
+        }
+    }
+}